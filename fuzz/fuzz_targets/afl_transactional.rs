@@ -8,7 +8,9 @@ use arbitrary::Unstructured;
 use move_smith::{
     config::Config,
     execution::{
-        transactional::{TransactionalExecutor, TransactionalInputBuilder, TransactionalResult},
+        transactional::{
+            CommonRunConfig, TransactionalExecutor, TransactionalInputBuilder, TransactionalResult,
+        },
         ExecutionManager,
     },
     CodeGenerator, MoveSmith,
@@ -38,7 +40,10 @@ fn main() {
         };
         let code = smith.get_compile_unit().emit_code();
         let mut input_builder = TransactionalInputBuilder::new();
-        let input = input_builder.set_code(&code).with_default_run().build();
+        let input = input_builder
+            .set_code(&code)
+            .with_common_runs(&CommonRunConfig::default_for(&CONFIG.generation))
+            .build();
         let bug = RUNNER.lock().unwrap().execute_check_new_bug(&input);
         if bug.unwrap() {
             panic!("Found bug")
@@ -17,7 +17,10 @@ static CONFIG: Lazy<Config> = Lazy::new(|| {
     let config_path =
         env::var("MOVE_SMITH_CONFIG").unwrap_or_else(|_| "MoveSmith.toml".to_string());
     let config_path = PathBuf::from(config_path);
-    Config::from_toml_file_or_default(&config_path)
+    let mut config = Config::from_toml_file_or_default(&config_path);
+    // Compiler V1 cannot compile Move 2 code
+    config.generation.allow_move2 = false;
+    config
 });
 
 static RUNNER: Lazy<Mutex<ExecutionManager<TransactionalResult, TransactionalExecutor>>> =
@@ -25,6 +25,8 @@ static CONFIG: Lazy<Config> = Lazy::new(|| {
     let config_path = PathBuf::from(config_path);
     let mut config = Config::from_toml_file_or_default(&config_path);
     config.generation.num_inline_funcs = RandomNumber::new(0, 0, 0);
+    // Only V2 configurations are compared
+    config.generation.allow_move2 = true;
     config
 });
 
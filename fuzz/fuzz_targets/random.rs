@@ -8,7 +8,9 @@ use libfuzzer_sys::fuzz_target;
 use move_smith::{
     config::Config,
     execution::{
-        transactional::{TransactionalExecutor, TransactionalInputBuilder, TransactionalResult},
+        transactional::{
+            CommonRunConfig, TransactionalExecutor, TransactionalInputBuilder, TransactionalResult,
+        },
         ExecutionManager,
     },
     CodeGenerator, MoveSmith,
@@ -69,7 +71,10 @@ fuzz_target!(|data: &[u8]| {
     };
 
     let mut input_builder = TransactionalInputBuilder::new();
-    let input = input_builder.set_code(&code).with_default_run().build();
+    let input = input_builder
+        .set_code(&code)
+        .with_common_runs(&CommonRunConfig::default_for(&CONFIG.generation))
+        .build();
 
     let bug = RUNNER.lock().unwrap().execute_check_new_bug(&input);
     if bug.unwrap() {
@@ -22,7 +22,29 @@ use std::{cell::RefCell, collections::BTreeSet};
 pub struct CompileUnit {
     pub modules: Vec<Module>,
     pub scripts: Vec<Script>,
-    pub runs: Vec<Identifier>,
+    pub runs: Vec<Run>,
+    pub scenarios: Vec<Scenario>,
+    /// Accounts whose accumulated hash is initialized and checked
+    pub accounts: Vec<String>,
+}
+
+/// A `//# run` task that calls a runner function with the given signers.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub name: Identifier,
+    pub signers: Vec<String>,
+}
+
+/// A sequence of `//# run` tasks operating on one resource of an account
+/// across transactions, e.g. publish, mutate, read and remove.
+/// Each run can be followed by a `//# view` of the resource.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub account: String,
+    /// The fully qualified name of the resource, e.g. `0xCAFE::Module0::Struct1`
+    pub resource: String,
+    /// The runs of the scenario and whether to view the resource after each run
+    pub steps: Vec<(Run, bool)>,
 }
 
 /// A Move module.
@@ -31,10 +53,17 @@ pub struct Module {
     // pub attributes: Vec<Attributes>,
     // pub address: Option<LeadingNameAccess>,
     pub uses: Vec<Use>,
+    pub friends: Vec<Identifier>,
     pub name: Identifier,
     pub functions: Vec<RefCell<Function>>,
     pub structs: Vec<RefCell<StructDefinition>>,
+    pub enums: Vec<RefCell<EnumDefinition>>,
     pub constants: Vec<Constant>,
+    pub specs: Vec<SpecBlock>,
+    pub tests: Vec<UnitTest>,
+    /// Functions reading a field of a struct through their `self` parameter,
+    /// called with the receiver syntax `s.function3()` (Move 2 only)
+    pub accessors: Vec<Function>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,11 +73,15 @@ pub struct Use {
 }
 
 /// A simplified Move Script.
-/// The script only contains a `main` function.
-/// The `main` function only consists of a sequence of function calls.
+/// The script only contains a `main` function taking signers and primitive
+/// arguments, which are given by `signers` and `args` when the script is run.
+/// The `main` function only consists of function calls and recording their results.
 #[derive(Debug, Clone)]
 pub struct Script {
-    pub main: Vec<FunctionCall>,
+    pub uses: Vec<Use>,
+    pub main: Function,
+    pub signers: Vec<String>,
+    pub args: Vec<Expression>,
 }
 
 /// A function definition.
@@ -57,14 +90,40 @@ pub struct Script {
 #[derive(Debug, Clone)]
 pub struct Function {
     pub visibility: Visibility,
+    /// An `entry` function can also be called directly by a transaction
+    pub is_entry: bool,
     pub signature: FunctionSignature,
     pub body: Option<Block>,
 }
 
-/// The Visibility
+/// A `#[test]` function run by the Move unit test runner.
+/// Its signer parameters are given the addresses in `signers`:
+/// `#[test(s = @0xBEEF, s1 = @0xA11CE)]`.
+/// If `expected_failure` is set, the test passes only if it aborts at a known
+/// site: `#[expected_failure(abort_code = 3, location = 0xCAFE::Module1)]`.
+#[derive(Debug, Clone)]
+pub struct UnitTest {
+    pub signers: Vec<(Identifier, String)>,
+    pub expected_failure: Option<ExpectedFailure>,
+    pub function: Function,
+}
+
+/// The abort code and the module of a known abort site
 #[derive(Debug, Clone)]
-pub struct Visibility {
-    pub public: bool,
+pub struct ExpectedFailure {
+    pub abort_code: u64,
+    pub location: String,
+}
+
+/// The Visibility
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Visibility {
+    Private,
+    Public,
+    /// `public(friend)`: callable from the modules declared as friends
+    Friend,
+    /// `public(package)`: callable from the modules in the same package
+    Package,
 }
 
 /// A function signature.
@@ -75,6 +134,8 @@ pub struct FunctionSignature {
     pub name: Identifier,
     pub parameters: Vec<(Identifier, Type)>,
     pub return_type: Option<Type>,
+    /// The `u64` parameter bounding the depth of recursive calls, if any
+    pub fuel: Option<Identifier>,
     /// Keep track of what types a function needs to acquire
     /// Maps name of a struct to a block scope
     /// e.g. `Struct2 -> _block1` means while generating `_block1`, the `Struct2`
@@ -101,6 +162,10 @@ pub struct StructDefinition {
     pub name: Identifier,
     pub abilities: Vec<Ability>,
     pub type_parameters: TypeParameters,
+    /// A positional struct is declared as `S(u8, bool)` and its fields are
+    /// accessed as `s.0`, the field names are only used to keep track of the field types.
+    /// Only structs with fields can be positional.
+    pub positional: bool,
     pub fields: Vec<(Identifier, Type)>,
 }
 
@@ -110,17 +175,70 @@ impl HasType for StructDefinition {
     }
 }
 
+/// The definition of a Move 2 enum.
+/// Variant fields only have primitive types, so enums cannot create cyclic data.
+#[derive(Debug, Clone)]
+pub struct EnumDefinition {
+    pub name: Identifier,
+    pub abilities: Vec<Ability>,
+    pub variants: Vec<Variant>,
+}
+
+impl HasType for EnumDefinition {
+    fn get_type(&self) -> Type {
+        Type::Enum(self.name.clone())
+    }
+}
+
+/// A variant of an enum.
+/// A positional variant is declared as `V(u8, bool)`, its field names are only
+/// used to keep track of the field types.
+/// A variant without fields is declared as a plain `V`.
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: Identifier,
+    pub positional: bool,
+    pub fields: Vec<(Identifier, Type)>,
+}
+
 /// A statement in a function body.
 #[derive(Debug, Clone)]
 pub enum Statement {
-    // While(While),
-    // For(For),
-    // Break,
-    // Continue,
+    While(While),
+    Loop(Loop),
+    For(For),
+    Break,
+    Continue,
     Decl(Declaration),
+    Unpack(Unpack),
     Expr(Expression),
 }
 
+/// A `while` loop.
+/// The generator is responsible for making sure the loop terminates.
+#[derive(Debug, Clone)]
+pub struct While {
+    pub condition: Expression,
+    pub body: Block,
+}
+
+/// A Move 2 range loop: `for (var in start..end) { ... }`.
+/// The loop variable lives in its own scope and is only visible in the body.
+#[derive(Debug, Clone)]
+pub struct For {
+    pub var: Identifier,
+    pub start: Expression,
+    pub end: Expression,
+    pub body: Block,
+}
+
+/// An unconditional `loop`.
+/// The body must contain a reachable `break` for the loop to terminate.
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub body: Block,
+}
+
 /// Kinds of global resource storage operations
 #[derive(Debug, Clone, Arbitrary)]
 pub enum ResourceOperationKind {
@@ -139,6 +257,8 @@ pub struct ResourceOperation {
     pub kind: ResourceOperationKind,
     pub typ: Type,
     pub args: Vec<Expression>,
+    /// Emit global borrows as `&R[addr]` instead of `borrow_global<R>(addr)`
+    pub index_notation: bool,
 }
 
 /// An inline struct initialization.
@@ -146,6 +266,7 @@ pub struct ResourceOperation {
 pub struct StructPack {
     pub name: Identifier,
     pub type_args: TypeArgs,
+    pub positional: bool,
     pub fields: Vec<(Identifier, Expression)>,
 }
 
@@ -157,6 +278,66 @@ impl HasType for StructPack {
     }
 }
 
+/// An anonymous function: `|x, y| body`.
+/// Lambdas passed to inline functions can capture the locals of the enclosing
+/// scope. Lambdas used as function values are closures that only capture
+/// copies of values.
+#[derive(Debug, Clone)]
+pub struct Lambda {
+    pub params: Vec<Identifier>,
+    pub body: Block,
+}
+
+/// Construct an enum value: `Enum::V { f: e }`, `Enum::V(e)` or `Enum::V`.
+#[derive(Debug, Clone)]
+pub struct VariantPack {
+    pub enum_name: Identifier,
+    pub variant: Identifier,
+    pub positional: bool,
+    pub fields: Vec<(Identifier, Expression)>,
+}
+
+/// A `match` expression over an enum value.
+/// The generator is responsible for making the arms exhaustive.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub expr: Expression,
+    pub arms: Vec<MatchArm>,
+}
+
+/// A single arm of a `match`: `pattern if (guard) => body`.
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: MatchPattern,
+    pub guard: Option<Expression>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone)]
+pub enum MatchPattern {
+    Variant(VariantPattern),
+    Wildcard,
+}
+
+/// A pattern matching a single variant.
+/// If `has_rest` is set, the omitted fields are matched by a trailing `..`.
+#[derive(Debug, Clone)]
+pub struct VariantPattern {
+    pub enum_name: Identifier,
+    pub variant: Identifier,
+    pub positional: bool,
+    pub fields: Vec<(Identifier, FieldPattern)>,
+    pub has_rest: bool,
+}
+
+/// Test whether an enum value is one of the given variants: `(e is Enum::V1 | Enum::V2)`.
+#[derive(Debug, Clone)]
+pub struct VariantTest {
+    pub expr: Expression,
+    pub enum_name: Identifier,
+    pub variants: Vec<Identifier>,
+}
+
 /// Declare a new variable.
 /// Optionally initialize the variable with an expression.
 /// Currently type annotations will always be generated.
@@ -168,6 +349,36 @@ pub struct Declaration {
     pub emit_type: bool,
 }
 
+/// Destructure a struct value into new variables:
+/// `let StructN<T> { f1: x, f2: _, f3: StructM { .. }, .. } = value;`
+/// or `let StructN<T>(x, _, ..) = value;` for positional structs.
+#[derive(Debug, Clone)]
+pub struct Unpack {
+    pub pattern: StructPattern,
+    pub value: Expression,
+}
+
+/// A struct pattern on the LHS of an unpack.
+/// Fields are always bound explicitly (`f: x`) because identifiers are globally unique.
+/// If `has_rest` is set, the omitted fields are matched by a trailing `..`.
+/// For positional structs, only a suffix of the fields can be omitted.
+#[derive(Debug, Clone)]
+pub struct StructPattern {
+    pub name: Identifier,
+    pub type_args: TypeArgs,
+    pub positional: bool,
+    pub fields: Vec<(Identifier, FieldPattern)>,
+    pub has_rest: bool,
+}
+
+/// The pattern a single field is matched against.
+#[derive(Debug, Clone)]
+pub enum FieldPattern {
+    Var(Identifier),
+    Wildcard,
+    Struct(StructPattern),
+}
+
 /// An expression.
 #[derive(Debug, Clone)]
 pub enum Expression {
@@ -185,8 +396,19 @@ pub enum Expression {
     Reference(Box<Expression>),
     Dereference(Box<Expression>),
     MutReference(Box<Expression>),
+    /// Explicitly freeze a mutable reference, e.g. `freeze(var1)`
+    Freeze(Box<Expression>),
     Return(Option<Box<Expression>>),
     Abort(Box<Expression>),
+    Assert(Box<Assert>),
+    Cast(Box<Cast>),
+    FieldAccess(Box<FieldAccess>),
+    VariantPack(VariantPack),
+    Match(Box<Match>),
+    VariantTest(Box<VariantTest>),
+    Lambda(Box<Lambda>),
+    /// A function used as a value by its name, e.g. `function3`
+    FunctionValue(Identifier),
 
     // The following three are expressions but may contain let bindings
     Resource(ResourceOperation),
@@ -216,6 +438,10 @@ pub struct VectorOperation {
     pub op: VectorOperationKind,
     // The arguments to the operation, if needed
     pub args: Vec<Expression>,
+    // Emit with Move 2 syntax if possible, i.e. receiver style `v.length()`
+    // or index notation `&v[i]`, instead of calling `vector::length(&v)`.
+    // A dereferenced `&v[i]` is emitted as `v[i]`.
+    pub sugar: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -237,12 +463,14 @@ pub enum VectorOperationKind {
     Remove,
     SwapRemove,
     // The following requires lambda
-    // ForEach,
-    // ForEachRef,
-    // ForEachMut,
-    // Fold
-    // Map
-    // Filter
+    ForEach,
+    ForEachRef,
+    ForEachMut,
+    // Carries the type of the accumulator
+    Fold(Type),
+    // Carries the element type of the new vector
+    Map(Type),
+    Filter,
 }
 
 /// Represent the type of vector an operation requires i.e. the type of the first argument
@@ -277,6 +505,12 @@ impl VectorOperationKind {
             IndexOf => Ref,
             Remove => MutRef,
             SwapRemove => MutRef,
+            ForEach => Own,
+            ForEachRef => Ref,
+            ForEachMut => MutRef,
+            Fold(_) => Own,
+            Map(_) => Own,
+            Filter => Own,
         }
     }
 
@@ -306,6 +540,12 @@ impl VectorOperationKind {
             IndexOf => Some(Type::Tuple(vec![Type::Bool, Type::U64])),
             Remove => Some(elem_typ.clone()),
             SwapRemove => Some(elem_typ.clone()),
+            ForEach => None,
+            ForEachRef => None,
+            ForEachMut => None,
+            Fold(acc_typ) => Some(acc_typ.clone()),
+            Map(new_typ) => Some(Type::Vector(Box::new(new_typ.clone()))),
+            Filter => Some(vec_typ),
         }
     }
 
@@ -331,6 +571,34 @@ impl VectorOperationKind {
             IndexOf => vec![Type::Ref(Box::new(elem_typ.clone()))],
             Remove => vec![Type::U64],
             SwapRemove => vec![Type::U64],
+            // The lambda is always the last argument and is handled
+            // separately by `lambda_signature`
+            ForEach => vec![],
+            ForEachRef => vec![],
+            ForEachMut => vec![],
+            Fold(acc_typ) => vec![acc_typ.clone()],
+            Map(_) => vec![],
+            Filter => vec![],
+        }
+    }
+
+    /// Return the parameter types and the return type of the lambda
+    /// required by the operation, if any
+    pub fn lambda_signature(&self, elem_typ: &Type) -> Option<(Vec<Type>, Option<Type>)> {
+        use VectorOperationKind::*;
+        let elem_ref = Type::Ref(Box::new(elem_typ.clone()));
+        let elem_mutref = Type::MutRef(Box::new(elem_typ.clone()));
+        match self {
+            ForEach => Some((vec![elem_typ.clone()], None)),
+            ForEachRef => Some((vec![elem_ref], None)),
+            ForEachMut => Some((vec![elem_mutref], None)),
+            Fold(acc_typ) => Some((
+                vec![acc_typ.clone(), elem_typ.clone()],
+                Some(acc_typ.clone()),
+            )),
+            Map(new_typ) => Some((vec![elem_typ.clone()], Some(new_typ.clone()))),
+            Filter => Some((vec![elem_ref], Some(Type::Bool))),
+            _ => None,
         }
     }
 }
@@ -402,10 +670,38 @@ pub enum EqualityBinaryOperator {
     Neq,
 }
 
-/// An assignment expression
+/// A numerical cast: `(expr as typ)`.
+/// Casting to a narrower type aborts with `ARITHMETIC_ERROR` if the value does not fit.
+#[derive(Debug, Clone)]
+pub struct Cast {
+    pub expr: Expression,
+    pub typ: Type,
+}
+
+/// Selecting a field of a struct value or reference.
+/// Depending on `kind`, this is `s.f`, `&s.f` or `&mut s.f`.
+#[derive(Debug, Clone)]
+pub struct FieldAccess {
+    pub kind: FieldAccessKind,
+    pub base: Expression,
+    pub field: Identifier,
+    /// The index of the field of a positional struct, accessed as `s.0`
+    pub position: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldAccessKind {
+    Value,
+    Borrow,
+    MutBorrow,
+}
+
+/// An assignment expression.
+/// If `op` is set, this is a compound assignment, e.g. `lhs += rhs`.
 #[derive(Debug, Clone)]
 pub struct Assignment {
     pub lhs: Expression,
+    pub op: Option<NumericalBinaryOperator>,
     pub rhs: Expression,
 }
 
@@ -424,7 +720,16 @@ pub struct FunctionCall {
     pub name: Identifier,
     pub type_args: TypeArgs,
     pub args: Vec<Expression>,
+    /// Emit with the receiver syntax, i.e. `arg0.name(arg1, ...)`
+    pub receiver: bool,
 }
+/// An `assert!` that aborts with `code` if `condition` does not hold.
+#[derive(Debug, Clone)]
+pub struct Assert {
+    pub condition: Expression,
+    pub code: Expression,
+}
+
 /// A constant
 #[derive(Debug, Clone)]
 pub struct Constant {
@@ -433,11 +738,125 @@ pub struct Constant {
     pub value: Expression,
 }
 
+/// A specification block: `spec function3 { ... }`.
+/// Specifications are only type checked by the compilers, they are never
+/// verified, so the conditions do not have to hold.
+#[derive(Debug, Clone)]
+pub struct SpecBlock {
+    pub target: SpecTarget,
+    pub members: Vec<SpecMember>,
+}
+
+/// The module member a specification block is attached to.
+#[derive(Debug, Clone)]
+pub enum SpecTarget {
+    Module,
+    Function(Identifier),
+    Struct(Identifier),
+}
+
+#[derive(Debug, Clone)]
+pub enum SpecMember {
+    Condition(SpecCondition),
+    Function(SpecFunction),
+}
+
+/// A condition such as `aborts_if var1 > 10;`
+#[derive(Debug, Clone)]
+pub struct SpecCondition {
+    pub kind: SpecConditionKind,
+    pub expr: SpecExpression,
+}
+
+#[derive(Debug, Clone)]
+pub enum SpecConditionKind {
+    Requires,
+    AbortsIf,
+    Ensures,
+    Invariant,
+}
+
+/// A helper function only usable in specifications: `fun function5(var1: u64): bool { ... }`
+#[derive(Debug, Clone)]
+pub struct SpecFunction {
+    pub name: Identifier,
+    pub params: Vec<(Identifier, Type)>,
+    pub return_type: Type,
+    pub body: SpecExpression,
+}
+
+/// An expression of the specification language.
+/// Integers are unbounded in specifications, so number literals have no suffix
+/// and arithmetic never aborts.
+#[derive(Debug, Clone)]
+pub enum SpecExpression {
+    Boolean(bool),
+    NumberLiteral(BigUint),
+    AddressLiteral(String),
+    Variable(Identifier),
+    /// The return value of the function, only available in `ensures`
+    Result,
+    /// A call to a spec helper function or a pure library function
+    Call(Identifier, Vec<SpecExpression>),
+    /// The value of an expression before the function is called, only available in `ensures`
+    Old(Box<SpecExpression>),
+    /// `global<T>(addr)`
+    Global(Type, Box<SpecExpression>),
+    /// `exists<T>(addr)`
+    Exists(Type, Box<SpecExpression>),
+    FieldAccess(Box<SpecExpression>, Identifier),
+    Not(Box<SpecExpression>),
+    BinaryOperation(Box<SpecBinaryOperation>),
+    Quantifier(Box<Quantifier>),
+}
+
+#[derive(Debug, Clone)]
+pub struct SpecBinaryOperation {
+    pub op: SpecBinaryOperator,
+    pub lhs: SpecExpression,
+    pub rhs: SpecExpression,
+}
+
+#[derive(Debug, Clone)]
+pub enum SpecBinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Le,
+    Ge,
+    Leq,
+    Geq,
+    Eq,
+    Neq,
+    And,
+    Or,
+    Implies,
+}
+
+/// `forall x: u64: body` or `exists x in lo..hi: body`.
+/// If `range` is set, the variable ranges over the integers in `lo..hi`.
+#[derive(Debug, Clone)]
+pub struct Quantifier {
+    pub kind: QuantifierKind,
+    pub var: Identifier,
+    pub typ: Type,
+    pub range: Option<(SpecExpression, SpecExpression)>,
+    pub body: SpecExpression,
+}
+
+#[derive(Debug, Clone, Arbitrary)]
+pub enum QuantifierKind {
+    Forall,
+    Exists,
+}
+
 type ExprFilter = fn(&Expression) -> bool;
 
 #[derive(Debug, Clone, Default)]
 struct ExprCollector<'a> {
     exprs: Vec<&'a Expression>,
+    /// The types of the variables declared with `let`
+    decl_typs: Vec<&'a Type>,
     filter: Option<ExprFilter>,
 }
 
@@ -445,6 +864,7 @@ impl<'a> ExprCollector<'a> {
     fn new(filter: Option<ExprFilter>) -> Self {
         Self {
             exprs: Vec::new(),
+            decl_typs: Vec::new(),
             filter,
         }
     }
@@ -467,13 +887,30 @@ impl<'a> ExprCollector<'a> {
     fn visit_statement(&mut self, stmt: &'a Statement) {
         match stmt {
             Statement::Decl(decl) => {
+                self.decl_typs.extend(decl.typs.iter());
                 if let Some(value) = &decl.value {
                     self.visit_expr(value);
                 }
             },
+            Statement::Unpack(unpack) => {
+                self.visit_expr(&unpack.value);
+            },
             Statement::Expr(e) => {
                 self.visit_expr(e);
             },
+            Statement::While(w) => {
+                self.visit_expr(&w.condition);
+                self.visit_block(&w.body);
+            },
+            Statement::Loop(l) => {
+                self.visit_block(&l.body);
+            },
+            Statement::For(f) => {
+                self.visit_expr(&f.start);
+                self.visit_expr(&f.end);
+                self.visit_block(&f.body);
+            },
+            Statement::Break | Statement::Continue => (),
         }
     }
 
@@ -524,6 +961,9 @@ impl<'a> ExprCollector<'a> {
             Expression::MutReference(e) => {
                 self.visit_expr(e);
             },
+            Expression::Freeze(e) => {
+                self.visit_expr(e);
+            },
             Expression::Resource(rop) => {
                 for e in rop.args.iter() {
                     self.visit_expr(e);
@@ -545,6 +985,36 @@ impl<'a> ExprCollector<'a> {
             Expression::Abort(e) => {
                 self.visit_expr(e);
             },
+            Expression::Assert(assert) => {
+                self.visit_expr(&assert.condition);
+                self.visit_expr(&assert.code);
+            },
+            Expression::Cast(cast) => {
+                self.visit_expr(&cast.expr);
+            },
+            Expression::FieldAccess(access) => {
+                self.visit_expr(&access.base);
+            },
+            Expression::VariantPack(pack) => {
+                for (_, expr) in &pack.fields {
+                    self.visit_expr(expr);
+                }
+            },
+            Expression::Match(m) => {
+                self.visit_expr(&m.expr);
+                for arm in &m.arms {
+                    if let Some(guard) = &arm.guard {
+                        self.visit_expr(guard);
+                    }
+                    self.visit_block(&arm.body);
+                }
+            },
+            Expression::VariantTest(test) => {
+                self.visit_expr(&test.expr);
+            },
+            Expression::Lambda(lambda) => {
+                self.visit_block(&lambda.body);
+            },
             _ => (),
         }
     }
@@ -556,4 +1026,11 @@ impl Function {
         collector.visit_function(self);
         collector.exprs
     }
+
+    /// Get the types of all variables declared with `let` in the body
+    pub fn all_decl_types(&self) -> Vec<&Type> {
+        let mut collector = ExprCollector::new(None);
+        collector.visit_function(self);
+        collector.decl_typs
+    }
 }
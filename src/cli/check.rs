@@ -120,7 +120,7 @@ pub fn handle_check(env: &MoveSmithEnv, cmd: &Check) {
     executor.set_save_input(true);
 
     let loaded_num = Mutex::new(0usize);
-    let run_config = env.cli.global_options.run.clone().unwrap_or_default();
+    let run_config = env.run_config();
     let mut to_execute: Vec<(PathBuf, TransactionalInput)> = all_moves
         .par_iter()
         .filter_map(|move_file| {
@@ -49,13 +49,17 @@ pub fn handle_generate(env: &MoveSmithEnv, cmd: &Generate) {
     if !cmd.skip_run {
         println!("[2/2] Running transactional tests...");
         let executor = ExecutionManager::<TransactionalResult, TransactionalExecutor>::default();
+        let run_config = env.run_config();
         let pb = get_progress_bar_with_msg(cmd.num, "Running");
         let timer = Instant::now();
         let results = codes
             .par_iter()
             .map(|code| {
                 let mut input_buidler = TransactionalInputBuilder::new();
-                let input = input_buidler.set_code(code).with_default_run().build();
+                let input = input_buidler
+                    .set_code(code)
+                    .with_common_runs(&run_config)
+                    .build();
                 let result = executor.execute_check_new_bug(&input);
                 pb.inc(1);
                 result.unwrap_or(false)
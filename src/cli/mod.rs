@@ -63,6 +63,9 @@ pub struct Run {
     /// Run with all configurations
     #[arg(default_value = "false", long)]
     pub run_all: bool,
+    /// Run the `#[test]` functions with the unit test runner instead
+    #[arg(default_value = "false", long)]
+    pub unit_test: bool,
     /// Format to show the output
     #[arg(
         value_name = "OUTPUT_MODE",
@@ -167,4 +170,13 @@ impl MoveSmithEnv {
         let config = Config::from_toml_file_or_default(&cli.global_options.config);
         MoveSmithEnv { cli, config }
     }
+
+    /// The runs given by `--run`, or the default runs for the generation config
+    pub fn run_config(&self) -> CommonRunConfig {
+        self.cli
+            .global_options
+            .run
+            .clone()
+            .unwrap_or_else(|| CommonRunConfig::default_for(&self.config.generation))
+    }
 }
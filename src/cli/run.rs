@@ -1,43 +1,37 @@
 // Copyright (c) Aptos Foundation
 // SPDX-License-Identifier: Apache-2.0
 
-//! Execute a Move file or a raw byte file.
+//! Execute a Move file or a raw byte file, as a transactional test or as unit tests.
 
 use crate::{
     cli::{raw2move::raw2move, MoveSmithEnv, OutputMode, Run},
     execution::{
         transactional::{
-            result::ResultStatus, CommonRunConfig, TransactionalExecutor,
-            TransactionalInputBuilder, TransactionalResult,
+            result::ResultStatus, TransactionalExecutor, TransactionalInputBuilder,
+            TransactionalResult,
         },
+        unit_test::{UnitTestExecutor, UnitTestInput, UnitTestResult},
         ExecutionManager,
     },
 };
 use std::{fs, path::PathBuf};
 
 pub fn handle_run(env: &MoveSmithEnv, cmd: &Run) {
+    if cmd.unit_test {
+        handle_unit_test_run(env, cmd);
+        return;
+    }
     let executor = ExecutionManager::<TransactionalResult, TransactionalExecutor>::default();
 
     let mut input_builder = TransactionalInputBuilder::new();
     match fs::read_to_string(&cmd.file) {
         Ok(_) => input_builder.load_code_from_file(PathBuf::from(&cmd.file)),
-        Err(_) => {
-            println!("Converting: {:?} to Move", cmd.file);
-            let bytes = fs::read(&cmd.file).unwrap();
-            let (success, log, code) = raw2move(&env.config.generation, &bytes);
-            if !success {
-                println!("Failed to convert raw bytes to Move code:\n{}", log);
-                return;
-            }
-            input_builder.set_code(&code)
+        Err(_) => match convert_raw(env, cmd) {
+            Some(code) => input_builder.set_code(&code),
+            None => return,
         },
     };
-    let run_config = env
-        .cli
-        .global_options
-        .run
-        .clone()
-        .unwrap_or(CommonRunConfig::default());
+    let run_config = env.run_config();
     input_builder.with_common_runs(&run_config);
     let input = input_builder.build();
 
@@ -81,3 +75,52 @@ pub fn handle_run(env: &MoveSmithEnv, cmd: &Run) {
     }
     println!("Done!");
 }
+
+/// Run the `#[test]` functions of a Move file with both compilers,
+/// or only with V2 if the generated code may use Move 2 features
+fn handle_unit_test_run(env: &MoveSmithEnv, cmd: &Run) {
+    let executor = ExecutionManager::<UnitTestResult, UnitTestExecutor>::default();
+
+    let mut input = match fs::read_to_string(&cmd.file) {
+        Ok(_) => UnitTestInput::load_code_from_file(PathBuf::from(&cmd.file)),
+        Err(_) => match convert_raw(env, cmd) {
+            Some(code) => UnitTestInput::new(&code, None),
+            None => return,
+        },
+    };
+    // Move 2 code cannot be compiled by V1
+    input.set_compare_v1(!env.config.generation.allow_move2);
+
+    println!("Loaded code from file: {:?}", cmd.file);
+    let result = match executor.execute(&input) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("{:?}", e);
+            return;
+        },
+    };
+    match cmd.output {
+        OutputMode::Raw | OutputMode::Split => {
+            println!("{}", result.log);
+            println!("{:?}", result.status);
+            println!("Duration: {:?}", result.duration);
+        },
+        OutputMode::Canonicalized => {
+            println!("{}", result);
+        },
+        OutputMode::None => (),
+    }
+    println!("Done!");
+}
+
+/// Convert a raw input file to Move code
+fn convert_raw(env: &MoveSmithEnv, cmd: &Run) -> Option<String> {
+    println!("Converting: {:?} to Move", cmd.file);
+    let bytes = fs::read(&cmd.file).unwrap();
+    let (success, log, code) = raw2move(&env.config.generation, &bytes);
+    if !success {
+        println!("Failed to convert raw bytes to Move code:\n{}", log);
+        return None;
+    }
+    Some(code)
+}
@@ -1 +0,0 @@
-//# view --address 0xBEEF --resource 0xCAFE::FuzzStore::AccumulatedHash
@@ -3,13 +3,12 @@
 
 use crate::{
     ast::*,
-    names::{Identifier, IdentifierKind as IDKind},
+    names::Identifier,
     types::{Ability, StructTypeConcrete, Type, TypeArgs, TypeParameter, TypeParameters},
 };
-use std::{collections::BTreeSet, vec};
+use std::vec;
 
 static PROLOGUE: &str = include_str!("prologue.move");
-static EPILOGUE: &str = include_str!("epilogue.move");
 
 /// Generates Move source code from an AST.
 /// `emit_code_lines` should be implemented for each AST node.
@@ -78,7 +77,18 @@ impl CodeGenerator for CompileUnit {
     fn emit_code_lines(&self) -> Vec<String> {
         let mut code = Vec::new();
         code.push(PROLOGUE.to_string());
+        for account in &self.accounts {
+            code.push(format!(
+                "//# run 0xCAFE::FuzzStore::init_accumulated_hash --signers {}\n",
+                account
+            ));
+        }
         code.push('\n'.to_string());
+        // All modules are published together so that `friend` declarations
+        // and `public(package)` calls can refer to modules published after them
+        if !self.modules.is_empty() {
+            code.push("//# publish".to_string());
+        }
         for m in &self.modules {
             code.extend(m.emit_code_lines());
         }
@@ -89,11 +99,33 @@ impl CodeGenerator for CompileUnit {
 
         for r in &self.runs {
             code.push(format!(
-                "//# run {} --signers 0xBEEF --gas-budget 100000\n",
-                r.name
+                "//# run {} --signers {} --gas-budget 100000\n",
+                r.name,
+                r.signers.join(" ")
+            ));
+        }
+        // The scenarios run after all runners so that their steps are not interleaved
+        for s in &self.scenarios {
+            for (r, view) in &s.steps {
+                code.push(format!(
+                    "//# run {} --signers {} --gas-budget 100000\n",
+                    r.name,
+                    r.signers.join(" ")
+                ));
+                if *view {
+                    code.push(format!(
+                        "//# view --address {} --resource {}\n",
+                        s.account, s.resource
+                    ));
+                }
+            }
+        }
+        for account in &self.accounts {
+            code.push(format!(
+                "//# view --address {} --resource 0xCAFE::FuzzStore::AccumulatedHash",
+                account
             ));
         }
-        code.push(EPILOGUE.to_string());
         code
     }
 }
@@ -101,29 +133,28 @@ impl CodeGenerator for CompileUnit {
 impl CodeGenerator for Script {
     fn emit_code_lines(&self) -> Vec<String> {
         // The `//# run` is for the transactional test
-        let mut code = vec!["//# run".to_string(), "script {".to_string()];
-        let main = Function {
-            signature: FunctionSignature {
-                inline: false,
-                name: Identifier::new_str("main", IDKind::Function),
-                parameters: Vec::new(),
-                type_parameters: TypeParameters::default(),
-                return_type: None,
-                acquires: BTreeSet::new(),
-            },
-            visibility: Visibility { public: false },
-            // Hardcode one function to simplify the output
-            body: Some(Block {
-                name: Identifier::new_str("_block_script", IDKind::Block),
-                stmts: self
-                    .main
-                    .iter()
-                    .map(|f| Statement::Expr(Expression::FunctionCall(f.clone())))
-                    .collect(),
-                return_expr: None,
-            }),
+        // The arguments are passed to `main` after the signer
+        let args = self
+            .args
+            .iter()
+            .map(|arg| format!(" {}", arg.inline()))
+            .collect::<String>();
+        let args = match args.is_empty() {
+            true => "".to_string(),
+            false => format!(" --args{}", args),
         };
-        let main_code = main.emit_code_lines();
+        let mut code = vec![
+            format!(
+                "//# run --signers {}{} --gas-budget 100000",
+                self.signers.join(" "),
+                args
+            ),
+            "script {".to_string(),
+        ];
+        for u in &self.uses {
+            append_code_lines_with_indentation(&mut code, u.emit_code_lines(), INDENTATION_SIZE);
+        }
+        let main_code = self.main.emit_code_lines();
         append_code_lines_with_indentation(&mut code, main_code, INDENTATION_SIZE);
         code.push("}\n".to_string());
         code
@@ -133,16 +164,21 @@ impl CodeGenerator for Script {
 /// Output struct definitions and then function definitions in a module.
 impl CodeGenerator for Module {
     fn emit_code_lines(&self) -> Vec<String> {
-        // The `//# publish` is for the transactional test
-        let mut code = vec![
-            "//# publish".to_string(),
-            format!("module 0xCAFE::{} {{", self.name.emit_code()),
-        ];
+        // The `//# publish` for the transactional test is emitted by the compile unit
+        let mut code = vec![format!("module 0xCAFE::{} {{", self.name.emit_code())];
 
         for u in &self.uses {
             append_code_lines_with_indentation(&mut code, u.emit_code_lines(), INDENTATION_SIZE);
         }
 
+        for f in &self.friends {
+            append_code_lines_with_indentation(
+                &mut code,
+                vec![format!("friend 0xCAFE::{};", f.inline())],
+                INDENTATION_SIZE,
+            );
+        }
+
         for c in &self.constants {
             append_code_lines_with_indentation(&mut code, c.emit_code_lines(), INDENTATION_SIZE);
         }
@@ -155,6 +191,18 @@ impl CodeGenerator for Module {
             )
         }
 
+        for e in &self.enums {
+            append_code_lines_with_indentation(
+                &mut code,
+                e.borrow().emit_code_lines(),
+                INDENTATION_SIZE,
+            )
+        }
+
+        for f in &self.accessors {
+            append_code_lines_with_indentation(&mut code, f.emit_code_lines(), INDENTATION_SIZE);
+        }
+
         for f in &self.functions {
             append_code_lines_with_indentation(
                 &mut code,
@@ -163,6 +211,14 @@ impl CodeGenerator for Module {
             )
         }
 
+        for t in &self.tests {
+            append_code_lines_with_indentation(&mut code, t.emit_code_lines(), INDENTATION_SIZE);
+        }
+
+        for s in &self.specs {
+            append_code_lines_with_indentation(&mut code, s.emit_code_lines(), INDENTATION_SIZE);
+        }
+
         code.push("}\n".to_string());
         code
     }
@@ -190,6 +246,24 @@ impl CodeGenerator for StructDefinition {
                 format!("has {} ", abilities)
             },
         };
+        if self.positional {
+            let types = self
+                .fields
+                .iter()
+                .map(|(_, typ)| typ.emit_code())
+                .collect::<Vec<String>>();
+            let abilities = match abilities.is_empty() {
+                true => abilities,
+                false => format!(" {}", abilities.trim_end()),
+            };
+            return vec![format!(
+                "struct {}{}({}){};\n",
+                self.name.emit_code(),
+                type_params,
+                types.join(", "),
+                abilities
+            )];
+        }
         let mut code = vec![format!(
             "struct {}{} {}{{",
             self.name.emit_code(),
@@ -211,6 +285,58 @@ impl CodeGenerator for StructDefinition {
     }
 }
 
+impl CodeGenerator for EnumDefinition {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let abilities = match self.abilities.len() {
+            0 => "".to_string(),
+            _ => {
+                let abilities = self
+                    .abilities
+                    .iter()
+                    .map(|ability| ability.emit_code())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("has {} ", abilities)
+            },
+        };
+        let mut code = vec![format!("enum {} {}{{", self.name.emit_code(), abilities)];
+
+        let variants_code = self
+            .variants
+            .iter()
+            .map(|v| format!("{},", v.inline()))
+            .collect::<Vec<String>>();
+        append_code_lines_with_indentation(&mut code, variants_code, INDENTATION_SIZE);
+        code.push("}\n".to_string());
+        code
+    }
+}
+
+impl CodeGenerator for Variant {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let fields = match (self.fields.is_empty(), self.positional) {
+            (true, _) => "".to_string(),
+            (false, true) => {
+                let types = self
+                    .fields
+                    .iter()
+                    .map(|(_, typ)| typ.emit_code())
+                    .collect::<Vec<String>>();
+                format!("({})", types.join(", "))
+            },
+            (false, false) => {
+                let fields = self
+                    .fields
+                    .iter()
+                    .map(|(name, typ)| format!("{}: {}", name.emit_code(), typ.emit_code()))
+                    .collect::<Vec<String>>();
+                format!(" {{ {} }}", fields.join(", "))
+            },
+        };
+        vec![format!("{}{}", self.name.emit_code(), fields)]
+    }
+}
+
 impl CodeGenerator for Ability {
     fn emit_code_lines(&self) -> Vec<String> {
         match self {
@@ -246,6 +372,28 @@ impl CodeGenerator for TypeParameter {
     }
 }
 
+impl CodeGenerator for UnitTest {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let signers = self
+            .signers
+            .iter()
+            .map(|(name, addr)| format!("{} = @{}", name.emit_code(), addr))
+            .collect::<Vec<String>>();
+        let mut code = match signers.is_empty() {
+            true => vec!["#[test]".to_string()],
+            false => vec![format!("#[test({})]", signers.join(", "))],
+        };
+        if let Some(failure) = &self.expected_failure {
+            code.push(format!(
+                "#[expected_failure(abort_code = {}, location = {})]",
+                failure.abort_code, failure.location
+            ));
+        }
+        code.extend(self.function.emit_code_lines());
+        code
+    }
+}
+
 /// The logic to generate function signature is implemented here.
 impl CodeGenerator for Function {
     fn emit_code_lines(&self) -> Vec<String> {
@@ -267,12 +415,14 @@ impl CodeGenerator for Function {
             None => "".to_string(),
         };
 
-        let visibility = if self.visibility.public {
-            "public "
-        } else {
-            ""
+        let visibility = match self.visibility {
+            Visibility::Private => "",
+            Visibility::Public => "public ",
+            Visibility::Friend => "public(friend) ",
+            Visibility::Package => "public(package) ",
         };
 
+        let entry = if self.is_entry { "entry " } else { "" };
         let inline = if self.signature.inline { "inline " } else { "" };
 
         let type_params = self.signature.type_parameters.inline();
@@ -291,8 +441,9 @@ impl CodeGenerator for Function {
         };
 
         let mut code = vec![format!(
-            "{}{}fun {}{}({}){}{}",
+            "{}{}{}fun {}{}({}){}{}",
             visibility,
+            entry,
             inline,
             self.signature.name.emit_code(),
             type_params,
@@ -336,6 +487,7 @@ impl CodeGenerator for Statement {
     fn emit_code_lines(&self) -> Vec<String> {
         match self {
             Statement::Decl(decl) => decl.emit_code_lines(),
+            Statement::Unpack(unpack) => unpack.emit_code_lines(),
             Statement::Expr(expr) => {
                 let mut code = expr.emit_code_lines();
                 if !code.is_empty() {
@@ -343,10 +495,59 @@ impl CodeGenerator for Statement {
                 }
                 code
             },
+            Statement::While(w) => {
+                let mut code = w.emit_code_lines();
+                code.last_mut().unwrap().push(';');
+                code
+            },
+            Statement::Loop(l) => {
+                let mut code = l.emit_code_lines();
+                code.last_mut().unwrap().push(';');
+                code
+            },
+            Statement::For(f) => {
+                let mut code = f.emit_code_lines();
+                code.last_mut().unwrap().push(';');
+                code
+            },
+            Statement::Break => vec!["break;".to_string()],
+            Statement::Continue => vec!["continue;".to_string()],
         }
     }
 }
 
+impl CodeGenerator for While {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let mut code = vec![format!("while ({})", self.condition.inline())];
+        let body = self.body.emit_code_lines();
+        append_block(&mut code, body, 0);
+        code
+    }
+}
+
+impl CodeGenerator for For {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let mut code = vec![format!(
+            "for ({} in {}..{})",
+            self.var.emit_code(),
+            self.start.inline(),
+            self.end.inline()
+        )];
+        let body = self.body.emit_code_lines();
+        append_block(&mut code, body, 0);
+        code
+    }
+}
+
+impl CodeGenerator for Loop {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let mut code = vec!["loop".to_string()];
+        let body = self.body.emit_code_lines();
+        append_block(&mut code, body, 0);
+        code
+    }
+}
+
 impl CodeGenerator for ResourceOperation {
     fn emit_code_lines(&self) -> Vec<String> {
         use ResourceOperationKind::*;
@@ -368,6 +569,14 @@ impl CodeGenerator for ResourceOperation {
             .collect::<Vec<String>>()
             .join(", ");
 
+        if self.index_notation {
+            match self.kind {
+                BorrowGlobal => return vec![format!("&{}[{}]", typ, args)],
+                BorrowGlobalMut => return vec![format!("&mut {}[{}]", typ, args)],
+                _ => (),
+            }
+        }
+
         vec![format!("{}<{}>({})", call, typ, args)]
     }
 }
@@ -405,6 +614,50 @@ impl CodeGenerator for Declaration {
     }
 }
 
+impl CodeGenerator for Unpack {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let mut code = vec![format!("let {} =", self.pattern.inline())];
+        let rhs = self.value.emit_code_lines();
+        append_block(&mut code, rhs, 0);
+        code.last_mut().unwrap().push(';');
+        code
+    }
+}
+
+impl CodeGenerator for StructPattern {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let mut fields = self
+            .fields
+            .iter()
+            .map(|(field, pat)| {
+                let pat = match pat {
+                    FieldPattern::Var(var) => var.emit_code(),
+                    FieldPattern::Wildcard => "_".to_string(),
+                    FieldPattern::Struct(st) => st.inline(),
+                };
+                match self.positional {
+                    true => pat,
+                    false => format!("{}: {}", field.emit_code(), pat),
+                }
+            })
+            .collect::<Vec<String>>();
+        if self.has_rest {
+            fields.push("..".to_string());
+        }
+        let body = match (fields.is_empty(), self.positional) {
+            (_, true) => format!("({})", fields.join(", ")),
+            (true, false) => " {}".to_string(),
+            (false, false) => format!(" {{ {} }}", fields.join(", ")),
+        };
+        vec![format!(
+            "{}{}{}",
+            self.name.emit_code(),
+            self.type_args.inline(),
+            body
+        )]
+    }
+}
+
 impl CodeGenerator for Expression {
     fn emit_code_lines(&self) -> Vec<String> {
         match self {
@@ -420,8 +673,23 @@ impl CodeGenerator for Expression {
             Expression::UnaryOperation(uop) => uop.emit_code_lines(),
             Expression::IfElse(if_expr) => if_expr.emit_code_lines(),
             Expression::Reference(expr) => vec![format!("&({})", expr.inline())],
-            Expression::Dereference(expr) => vec![format!("*({})", expr.inline())],
+            Expression::Dereference(expr) => match expr.as_ref() {
+                // `*&v[i]` is emitted as the by-value index `v[i]`
+                Expression::VectorOperation(VectorOperation {
+                    op: VectorOperationKind::Borrow,
+                    args,
+                    sugar: true,
+                    ..
+                }) if matches!(args[0], Expression::Reference(_)) => match &args[0] {
+                    Expression::Reference(vec) => {
+                        vec![format!("{}[{}]", vec.inline(), args[1].inline())]
+                    },
+                    _ => unreachable!(),
+                },
+                _ => vec![format!("*({})", expr.inline())],
+            },
             Expression::MutReference(expr) => vec![format!("&mut ({})", expr.inline())],
+            Expression::Freeze(expr) => vec![format!("freeze({})", expr.inline())],
             Expression::Resource(rop) => rop.emit_code_lines(),
             Expression::VectorOperation(vop) => vop.emit_code_lines(),
             Expression::VectorLiteral(vlit) => vlit.emit_code_lines(),
@@ -430,6 +698,18 @@ impl CodeGenerator for Expression {
                 None => vec!["(return)".to_string()],
             },
             Expression::Abort(e) => vec![format!("(abort {})", e.inline())],
+            Expression::Assert(assert) => vec![format!(
+                "assert!({}, {})",
+                assert.condition.inline(),
+                assert.code.inline()
+            )],
+            Expression::Cast(cast) => cast.emit_code_lines(),
+            Expression::FieldAccess(access) => access.emit_code_lines(),
+            Expression::VariantPack(pack) => pack.emit_code_lines(),
+            Expression::Match(m) => m.emit_code_lines(),
+            Expression::VariantTest(test) => test.emit_code_lines(),
+            Expression::Lambda(lambda) => lambda.emit_code_lines(),
+            Expression::FunctionValue(name) => vec![name.emit_code()],
         }
     }
 }
@@ -492,16 +772,52 @@ impl CodeGenerator for VectorOperation {
             IndexOf => "index_of",
             Remove => "remove",
             SwapRemove => "swap_remove",
+            ForEach => "for_each",
+            ForEachRef => "for_each_ref",
+            ForEachMut => "for_each_mut",
+            Fold(_) => "fold",
+            Map(_) => "map",
+            Filter => "filter",
         };
 
+        // Receiver style calls and index notation auto-borrow the vector,
+        // so they are only used when the vector is passed by reference
+        let receiver = match self.args.first() {
+            Some(Expression::Reference(vec)) | Some(Expression::MutReference(vec))
+                if self.sugar =>
+            {
+                Some(vec.inline())
+            },
+            _ => None,
+        };
+
+        if let Some(vec) = &receiver {
+            match self.op {
+                Borrow => return vec![format!("&{}[{}]", vec, self.args[1].inline())],
+                BorrowMut => return vec![format!("&mut {}[{}]", vec, self.args[1].inline())],
+                _ => (),
+            }
+        }
+
+        let skip = receiver.is_some() as usize;
         let args = self
             .args
             .iter()
+            .skip(skip)
             .map(|arg| arg.inline())
             .collect::<Vec<String>>()
             .join(", ");
 
-        let typ = self.elem_typ.inline();
+        if let Some(vec) = receiver {
+            return vec![format!("{}.{}({})", vec, call, args)];
+        }
+
+        // `fold` and `map` have an extra type parameter for the value they produce
+        let typ = match &self.op {
+            Fold(acc_typ) => format!("{}, {}", acc_typ.inline(), self.elem_typ.inline()),
+            Map(new_typ) => format!("{}, {}", self.elem_typ.inline(), new_typ.inline()),
+            _ => self.elem_typ.inline(),
+        };
 
         vec![format!("vector::{}<{}>({})", call, typ, args)]
     }
@@ -551,9 +867,160 @@ impl CodeGenerator for BinaryOperation {
     }
 }
 
+impl CodeGenerator for Cast {
+    fn emit_code_lines(&self) -> Vec<String> {
+        vec![format!("({} as {})", self.expr.inline(), self.typ.inline())]
+    }
+}
+
+impl CodeGenerator for FieldAccess {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let prefix = match self.kind {
+            FieldAccessKind::Value => "",
+            FieldAccessKind::Borrow => "&",
+            FieldAccessKind::MutBorrow => "&mut ",
+        };
+        let field = match self.position {
+            Some(idx) => idx.to_string(),
+            None => self.field.emit_code(),
+        };
+        vec![format!("{}{}.{}", prefix, self.base.inline(), field)]
+    }
+}
+
+impl CodeGenerator for Lambda {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let params = self
+            .params
+            .iter()
+            .map(|p| p.emit_code())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let mut code = vec![format!("|{}|", params)];
+        append_block(&mut code, self.body.emit_code_lines(), 0);
+        code
+    }
+}
+
+impl CodeGenerator for VariantPack {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let path = format!(
+            "{}::{}",
+            self.enum_name.emit_code(),
+            self.variant.emit_code()
+        );
+        let fields = match (self.fields.is_empty(), self.positional) {
+            (true, _) => "".to_string(),
+            (false, true) => {
+                let args = self
+                    .fields
+                    .iter()
+                    .map(|(_, expr)| expr.inline())
+                    .collect::<Vec<String>>();
+                format!("({})", args.join(", "))
+            },
+            (false, false) => {
+                let fields = self
+                    .fields
+                    .iter()
+                    .map(|(name, expr)| format!("{}: {}", name.emit_code(), expr.inline()))
+                    .collect::<Vec<String>>();
+                format!(" {{ {} }}", fields.join(", "))
+            },
+        };
+        vec![format!("{}{}", path, fields)]
+    }
+}
+
+impl CodeGenerator for Match {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let mut code = vec![format!("match ({}) {{", self.expr.inline())];
+        let mut arms = Vec::new();
+        for arm in &self.arms {
+            let mut arm_code = arm.emit_code_lines();
+            arm_code.last_mut().unwrap().push(',');
+            arms.extend(arm_code);
+        }
+        append_code_lines_with_indentation(&mut code, arms, INDENTATION_SIZE);
+        code.push("}".to_string());
+        code
+    }
+}
+
+impl CodeGenerator for MatchArm {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let pattern = match &self.pattern {
+            MatchPattern::Variant(pat) => pat.inline(),
+            MatchPattern::Wildcard => "_".to_string(),
+        };
+        let guard = match &self.guard {
+            Some(guard) => format!(" if ({})", guard.inline()),
+            None => "".to_string(),
+        };
+        let mut code = vec![format!("{}{} =>", pattern, guard)];
+        append_block(&mut code, self.body.emit_code_lines(), 0);
+        code
+    }
+}
+
+impl CodeGenerator for VariantPattern {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let path = format!(
+            "{}::{}",
+            self.enum_name.emit_code(),
+            self.variant.emit_code()
+        );
+        let mut fields = self
+            .fields
+            .iter()
+            .map(|(name, pat)| {
+                let pat = match pat {
+                    FieldPattern::Var(var) => var.emit_code(),
+                    FieldPattern::Wildcard => "_".to_string(),
+                    FieldPattern::Struct(st) => st.inline(),
+                };
+                match self.positional {
+                    true => pat,
+                    false => format!("{}: {}", name.emit_code(), pat),
+                }
+            })
+            .collect::<Vec<String>>();
+        if self.has_rest {
+            fields.push("..".to_string());
+        }
+        let fields = match (fields.is_empty(), self.positional) {
+            (true, _) => "".to_string(),
+            (false, true) => format!("({})", fields.join(", ")),
+            (false, false) => format!(" {{ {} }}", fields.join(", ")),
+        };
+        vec![format!("{}{}", path, fields)]
+    }
+}
+
+impl CodeGenerator for VariantTest {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let variants = self
+            .variants
+            .iter()
+            .map(|v| format!("{}::{}", self.enum_name.emit_code(), v.emit_code()))
+            .collect::<Vec<String>>();
+        // Parenthesize the tested expression so that `is` cannot bind to
+        // a trailing sub-expression (e.g. the else branch of an `if`)
+        vec![format!(
+            "(({}) is {})",
+            self.expr.inline(),
+            variants.join(" | ")
+        )]
+    }
+}
+
 impl CodeGenerator for Assignment {
     fn emit_code_lines(&self) -> Vec<String> {
-        let mut code = vec![format!("{} =", self.lhs.emit_code(),)];
+        let op = match &self.op {
+            Some(op) => op.emit_code(),
+            None => "".to_string(),
+        };
+        let mut code = vec![format!("{} {}=", self.lhs.emit_code(), op)];
         let value = self.rhs.emit_code_lines();
         append_block(&mut code, value, 0);
         code
@@ -568,6 +1035,17 @@ impl CodeGenerator for StructPack {
             code.last_mut().unwrap().push_str(" {}");
             return code;
         }
+        if self.positional {
+            let args = self
+                .fields
+                .iter()
+                .map(|(_, expr)| expr.inline())
+                .collect::<Vec<String>>();
+            code.last_mut()
+                .unwrap()
+                .push_str(&format!("({})", args.join(", ")));
+            return code;
+        }
 
         let mut body = vec!["{".to_string()];
 
@@ -590,13 +1068,28 @@ impl CodeGenerator for StructPack {
 impl CodeGenerator for FunctionCall {
     fn emit_code_lines(&self) -> Vec<String> {
         let type_args = self.type_args.inline();
-        let mut code = vec![format!("{}{}(", self.name.emit_code(), type_args)];
-        if self.args.is_empty() {
+        // With the receiver syntax, the first argument is emitted before the name
+        let (mut code, call_args) = match self.receiver {
+            true => (
+                vec![format!(
+                    "{}.{}{}(",
+                    self.args[0].inline(),
+                    self.name.emit_code(),
+                    type_args
+                )],
+                &self.args[1..],
+            ),
+            false => (
+                vec![format!("{}{}(", self.name.emit_code(), type_args)],
+                &self.args[..],
+            ),
+        };
+        if call_args.is_empty() {
             code.last_mut().unwrap().push(')');
             return code;
         }
         let mut args = Vec::new();
-        for arg in &self.args {
+        for arg in call_args {
             let mut arg_lines = arg.emit_code_lines();
             arg_lines.last_mut().unwrap().push(',');
             args.extend(arg_lines);
@@ -718,11 +1211,35 @@ impl CodeGenerator for Type {
             },
             T::Struct(st) => st.name.inline(),
             T::StructConcrete(st) => st.inline(),
+            T::Enum(id) => id.inline(),
             T::TypeParameter(tp) => tp.name.inline(),
             T::Address => "address".to_string(),
             T::Signer => "signer".to_string(),
             T::Vector(t) => format!("vector<{}>", t.inline()),
-            _ => unimplemented!(),
+            T::Lambda(lt) => {
+                let params = lt
+                    .params
+                    .iter()
+                    .map(|t| t.inline())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("|{}| {}", params, lt.ret.inline())
+            },
+            T::Function(ft) => {
+                let params = ft
+                    .params
+                    .iter()
+                    .map(|t| t.inline())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let abilities = ft
+                    .abilities
+                    .iter()
+                    .map(|a| a.inline())
+                    .collect::<Vec<String>>()
+                    .join(" + ");
+                format!("|{}| {} has {}", params, ft.ret.inline(), abilities)
+            },
         }]
     }
 }
@@ -734,6 +1251,134 @@ impl CodeGenerator for StructTypeConcrete {
     }
 }
 
+impl CodeGenerator for SpecBlock {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let target = match &self.target {
+            SpecTarget::Module => "module".to_string(),
+            SpecTarget::Function(name) | SpecTarget::Struct(name) => name.emit_code(),
+        };
+        let mut code = vec![format!("spec {} {{", target)];
+        for member in &self.members {
+            let lines = match member {
+                SpecMember::Condition(cond) => cond.emit_code_lines(),
+                SpecMember::Function(func) => func.emit_code_lines(),
+            };
+            append_code_lines_with_indentation(&mut code, lines, INDENTATION_SIZE);
+        }
+        code.push("}\n".to_string());
+        code
+    }
+}
+
+impl CodeGenerator for SpecCondition {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let kind = match self.kind {
+            SpecConditionKind::Requires => "requires",
+            SpecConditionKind::AbortsIf => "aborts_if",
+            SpecConditionKind::Ensures => "ensures",
+            SpecConditionKind::Invariant => "invariant",
+        };
+        vec![format!("{} {};", kind, self.expr.inline())]
+    }
+}
+
+impl CodeGenerator for SpecFunction {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let params = self
+            .params
+            .iter()
+            .map(|(name, typ)| format!("{}: {}", name.emit_code(), typ.emit_code()))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let mut code = vec![format!(
+            "fun {}({}): {} {{",
+            self.name.emit_code(),
+            params,
+            self.return_type.emit_code()
+        )];
+        append_code_lines_with_indentation(
+            &mut code,
+            self.body.emit_code_lines(),
+            INDENTATION_SIZE,
+        );
+        code.push("}".to_string());
+        code
+    }
+}
+
+impl CodeGenerator for SpecExpression {
+    fn emit_code_lines(&self) -> Vec<String> {
+        use SpecExpression as E;
+        vec![match self {
+            E::Boolean(b) => b.to_string(),
+            E::NumberLiteral(n) => n.to_string(),
+            E::AddressLiteral(addr) => addr.clone(),
+            E::Variable(name) => name.emit_code(),
+            E::Result => "result".to_string(),
+            E::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.inline())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("{}({})", name.emit_code(), args)
+            },
+            E::Old(expr) => format!("old({})", expr.inline()),
+            E::Global(typ, addr) => format!("global<{}>({})", typ.inline(), addr.inline()),
+            E::Exists(typ, addr) => format!("exists<{}>({})", typ.inline(), addr.inline()),
+            E::FieldAccess(base, field) => format!("{}.{}", base.inline(), field.emit_code()),
+            E::Not(expr) => format!("!({})", expr.inline()),
+            E::BinaryOperation(binop) => binop.inline(),
+            E::Quantifier(quant) => quant.inline(),
+        }]
+    }
+}
+
+impl CodeGenerator for SpecBinaryOperation {
+    fn emit_code_lines(&self) -> Vec<String> {
+        use SpecBinaryOperator as OP;
+        let op = match self.op {
+            OP::Add => "+",
+            OP::Sub => "-",
+            OP::Mul => "*",
+            OP::Le => "<",
+            OP::Ge => ">",
+            OP::Leq => "<=",
+            OP::Geq => ">=",
+            OP::Eq => "==",
+            OP::Neq => "!=",
+            OP::And => "&&",
+            OP::Or => "||",
+            OP::Implies => "==>",
+        };
+        vec![format!(
+            "({} {} {})",
+            self.lhs.inline(),
+            op,
+            self.rhs.inline()
+        )]
+    }
+}
+
+impl CodeGenerator for Quantifier {
+    fn emit_code_lines(&self) -> Vec<String> {
+        let kind = match self.kind {
+            QuantifierKind::Forall => "forall",
+            QuantifierKind::Exists => "exists",
+        };
+        let binding = match &self.range {
+            Some((lo, hi)) => format!(
+                "{} in {}..{}",
+                self.var.emit_code(),
+                lo.inline(),
+                hi.inline()
+            ),
+            None => format!("{}: {}", self.var.emit_code(), self.typ.inline()),
+        };
+        vec![format!("({} {}: {})", kind, binding, self.body.inline())]
+    }
+}
+
 impl CodeGenerator for Constant {
     fn emit_code_lines(&self) -> Vec<String> {
         vec![format!(
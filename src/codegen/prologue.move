@@ -20,6 +20,12 @@ module 0xCAFE::FuzzStore {
         move_to<AccumulatedHash>(sref, acc);
     }
 
+    // Unit tests cannot run `init_accumulated_hash` as a transaction
+    #[test_only]
+    public fun init_accumulated_hash_for_test(sref: &signer) {
+        internal_init_accumulated_hash(sref);
+    }
+
     public fun record_value<T>(sref: &signer, x: &T) acquires AccumulatedHash {
         let acc = borrow_global_mut<AccumulatedHash>(signer::address_of(sref));
         vector::append(&mut acc.acc, bcs::to_bytes(x));
@@ -33,4 +39,3 @@ module 0xCAFE::FuzzStore {
     }
 }
 
-//# run 0xCAFE::FuzzStore::init_accumulated_hash --signers 0xBEEF 
@@ -17,32 +17,63 @@ pub struct Config {
 pub struct GenerationConfig {
     /// The number of `//# run 0xCAFE::ModuleX::funX` to invoke
     pub num_runs_per_func: RandomNumber,
+    /// The number of `#[test]` functions calling each function
+    pub num_unit_tests_per_func: RandomNumber,
+    /// Chance (in percent) for a unit test to be `#[expected_failure]`
+    pub expected_failure_percent: usize,
+    /// The number of multi-transaction scenarios on the resources of a module
+    pub num_scenarios_in_module: RandomNumber,
     /// The number of functions that can have `inline`
     pub num_inline_funcs: RandomNumber,
 
+    // Accounts that sign transactions and own global storage
+    pub num_accounts: RandomNumber,
     pub num_modules: RandomNumber,
     pub num_functions_in_module: RandomNumber,
     pub num_structs_in_module: RandomNumber,
+    /// The number of structs without `copy` and `drop` in a module
+    pub num_linear_structs_in_module: RandomNumber,
 
     pub num_fields_in_struct: RandomNumber,
     /// The maximum total number of fields in all structs that can have
     /// type of another struct
     pub num_fields_of_struct_type: RandomNumber,
 
+    // Enums are only generated for Move 2
+    pub num_enums_in_module: RandomNumber,
+    // Enums must have at least one variant
+    pub num_variants_in_enum: RandomNumber,
+    pub num_fields_in_variant: RandomNumber,
+
+    /// The number of constants in a module besides `ADDR`
+    pub num_constants_in_module: RandomNumber,
+
     // Includes all kinds of statements
     pub num_stmts_in_func: RandomNumber,
     // Addtionally insert some resource or vector operations
     pub num_additional_operations_in_func: RandomNumber,
 
     pub num_params_in_func: RandomNumber,
+    // Signer parameters in addition to the first `&signer` of each function,
+    // bounded by the number of accounts
+    pub num_signer_params_in_func: RandomNumber,
 
     // This has lowest priority
     // i.e. if the block is a function body
     // max_num_stmts_in_func will override this
     pub num_stmts_in_block: RandomNumber,
 
+    // Scripts are run as transactions after all modules are published
+    pub num_scripts: RandomNumber,
+    // Parameters of a script are signer and primitive transaction arguments
+    pub num_params_in_script: RandomNumber,
     pub num_calls_in_script: RandomNumber,
 
+    // Maximum number of iterations of a generated loop
+    pub num_iterations_in_loop: RandomNumber,
+    // Maximum nesting depth of loops in a function
+    pub max_loop_depth: usize,
+
     // Maximum depth of nested expression
     pub expr_depth: RandomNumber,
     // Maximum depth of nested type instantiation
@@ -56,11 +87,61 @@ pub struct GenerationConfig {
     // Timeout in seconds
     pub generation_timeout_sec: usize, // MoveSmith generation timeout
 
-    // Allow recursive calls in the generated code
+    // Allow unbounded recursive calls in the generated code
     pub allow_recursive_calls: bool,
+    // Generate constructs only supported by Move 2, e.g. `for` loops.
+    // Compiler V1 cannot parse them, so this must be off to compare V1 with V2
+    pub allow_move2: bool,
+    // Chance (in percent) for a function to take a `u64` fuel parameter.
+    // The functions with fuel in a module can call each other recursively,
+    // since each call passes on less fuel
+    pub fuel_func_percent: usize,
+    // Maximum fuel given to a function by a caller without fuel
+    pub max_fuel: u64,
 
     // Maximum number of bytes to construct hex or byte string
     pub hex_byte_str_size: RandomNumber,
+
+    // Chance (in percent) for a generated cast to be narrowing, which aborts
+    // if the value does not fit into the target type
+    pub narrowing_cast_percent: usize,
+
+    // Chance (in percent) for a function to take a parameter that must be
+    // consumed exactly once, either of a linear struct type or of a type
+    // parameter without `copy` or `drop`
+    pub linear_param_percent: usize,
+
+    // Chance (in percent) for a function to take `&mut` parameters of the same
+    // type and return a reference borrowed from one of them
+    pub borrow_func_percent: usize,
+    // Chance (in percent) for a struct to get an accessor function reading one of
+    // its fields, called with the receiver syntax `s.function3()` (Move 2 only)
+    pub accessor_percent: usize,
+    // Chance (in percent) for a function to take a `self` reference to a struct of
+    // its module first, so that it can be called with the receiver syntax (Move 2 only)
+    pub self_param_percent: usize,
+    // Chance (in percent) for a public or private function to be an `entry` function,
+    // which only takes signers and primitive values and returns nothing
+    pub entry_func_percent: usize,
+
+    // Chance (in percent) for a global storage operation in straight-line code
+    // to use a resource whose presence is known, so that it needs no `exists` check
+    pub known_resource_op_percent: usize,
+
+    // Chance (in percent) for a non-inline function to take a function value (Move 2 only)
+    pub function_value_param_percent: usize,
+    // Chance (in percent) for a field of a struct without `store` to hold a function value
+    // (Move 2 only)
+    pub function_value_field_percent: usize,
+
+    // Chance (in percent) for a function or struct to get a spec block
+    pub spec_block_percent: usize,
+    // Number of conditions or invariants in a spec block
+    pub num_conditions_in_spec: RandomNumber,
+    // Number of spec helper functions declared in the module spec block
+    pub num_spec_funs_in_module: RandomNumber,
+    // Maximum depth of nested spec expressions
+    pub max_spec_expr_depth: usize,
 }
 
 impl Default for Config {
@@ -4,7 +4,7 @@
 //! Manages the various information during generation
 
 use crate::{
-    ast::FunctionSignature,
+    ast::{Expression, FunctionSignature},
     config::GenerationConfig,
     names::{Identifier, IdentifierKind as IDKind, IdentifierPool, Scope},
     selection::RandomNumber,
@@ -21,6 +21,7 @@ pub struct Env {
     pub id_pool: IdentifierPool,
     pub type_pool: TypePool,
     pub live_vars: LiveVarPool,
+    pub storage: StorageModel,
 
     /// For controlling the depth of the generated expressions/types
     pub expr_depth: DepthRing,
@@ -36,9 +37,32 @@ pub struct Env {
     /// Number of fields that has type of another struct
     struct_type_field_counter: usize,
 
+    /// Number of loops enclosing the code currently being generated
+    loop_depth: usize,
+
+    /// Variables that can be read but must never be assigned or mutably
+    /// borrowed, e.g. the loop variable of a `for` loop
+    read_only_vars: BTreeSet<Identifier>,
+
+    /// Loop depths saved when entering lambda bodies.
+    /// `break` and `continue` cannot cross a lambda boundary, so the
+    /// loop depth restarts from zero inside a lambda.
+    lambda_loop_depths: Vec<usize>,
+
+    /// Variables that cannot be used at all for now, e.g. a vector that is
+    /// being iterated by a higher-order vector operation
+    hidden_vars: BTreeSet<Identifier>,
+
     pub curr_func_signature: Option<FunctionSignature>,
+
+    /// Accounts that can sign transactions and own global storage
+    pub accounts: Vec<String>,
 }
 
+/// Addresses of all accounts that can be used in a compile unit.
+/// Each account has its own accumulated hash in `FuzzStore`.
+pub const ACCOUNTS: [&str; 4] = ["0xBEEF", "0xA11CE", "0xB0B", "0xC0DE"];
+
 /// A ring buffer to keep track of the max depth of expression/types.
 /// We randomly generate a bunch of depths in the beginning and round-robin through them.
 /// The goal is to potentially use a very huge depth occasionally but not always, since
@@ -157,11 +181,12 @@ impl DepthRing {
     }
 }
 
-/// NOTE: This is unused for now to avoid the situation where the fuzzer cannot
-/// find any expression for a type. Now everything is copy+drop.
-///
 /// Keep track of if a variable is still alive within a certain scope
 ///
+/// Most values are copy+drop and are never marked as moved. The pool matters
+/// for linear values (without copy or drop), which are owned by the block
+/// where they are marked alive and must be consumed exactly once.
+///
 /// If a variable might be dead, it is dead.
 /// e.g. if a variable is consumer in one branch of an ITE, it is considered used.
 #[derive(Debug, Default)]
@@ -218,6 +243,73 @@ impl LiveVarPool {
     }
 }
 
+/// Keep track of the resources known to be published in global storage
+/// by the straight-line code of a function body.
+///
+/// Nothing is known at the start of a function, since the storage depends on
+/// the previous transactions. Addresses are identified by the code computing
+/// them, e.g. `@0xBEEF` or `signer::address_of(sref)`. Different code may
+/// compute the same address, so a change to a resource type forgets what was
+/// known about the type under any other address.
+#[derive(Debug, Default)]
+pub struct StorageModel {
+    /// The block whose statements are the straight-line code being tracked
+    scope: Option<Scope>,
+    /// Whether a resource type is published under an address, along with
+    /// the expression computing the address
+    facts: BTreeMap<(Type, String), (Expression, bool)>,
+}
+
+impl StorageModel {
+    /// Create an empty StorageModel
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking the statements of the block `scope` with nothing known
+    pub fn reset(&mut self, scope: Option<Scope>) {
+        self.scope = scope;
+        self.facts.clear();
+    }
+
+    /// Check if code generated in `scope` is the straight-line code being tracked
+    pub fn is_straight_line(&self, scope: &Scope) -> bool {
+        self.scope.as_ref() == Some(scope)
+    }
+
+    /// Return all the known facts as (resource type, address, published)
+    pub fn known_facts(&self) -> Vec<(Type, Expression, bool)> {
+        self.facts
+            .iter()
+            .map(|((typ, _), (addr, published))| (typ.clone(), addr.clone(), *published))
+            .collect()
+    }
+
+    /// Check if `typ` is known to be published under the address `key`
+    pub fn is_published(&self, typ: &Type, key: &str) -> Option<bool> {
+        self.facts
+            .get(&(typ.clone(), key.to_string()))
+            .map(|(_, published)| *published)
+    }
+
+    /// Record that `typ` is (or is not) published under the address `key`
+    pub fn set_published(&mut self, typ: &Type, key: String, addr: Expression, published: bool) {
+        trace!("Storage: {:?} published under {}: {}", typ, key, published);
+        self.forget_type(typ);
+        self.facts.insert((typ.clone(), key), (addr, published));
+    }
+
+    /// Forget everything known about `typ`
+    pub fn forget_type(&mut self, typ: &Type) {
+        self.facts.retain(|(t, _), _| t != typ);
+    }
+
+    /// Forget everything, e.g. after a call that may change the storage
+    pub fn forget_all(&mut self) {
+        self.facts.clear();
+    }
+}
+
 impl Env {
     /// Create a new environment with the given configuration
     pub fn new(config: &GenerationConfig) -> Self {
@@ -226,6 +318,7 @@ impl Env {
             id_pool: IdentifierPool::new(),
             type_pool: TypePool::new(),
             live_vars: LiveVarPool::new(),
+            storage: StorageModel::new(),
 
             expr_depth: DepthRing::new("expr".to_string()),
             type_depth: DepthRing::new("type".to_string()),
@@ -234,13 +327,24 @@ impl Env {
             timeout: std::time::Duration::from_secs(config.generation_timeout_sec as u64),
             inline_func_counter: 0,
             struct_type_field_counter: 0,
+            loop_depth: 0,
+            read_only_vars: BTreeSet::new(),
+            lambda_loop_depths: Vec::new(),
+            hidden_vars: BTreeSet::new(),
             curr_func_signature: None,
+            accounts: vec![ACCOUNTS[0].to_string()],
         }
     }
 
     pub fn initialize(&mut self, u: &mut Unstructured) {
         self.expr_depth.initialize(10, &self.config.expr_depth, u);
         self.type_depth.initialize(10, &self.config.type_depth, u);
+        let num_accounts = self.config.num_accounts.select(u).unwrap();
+        self.accounts = ACCOUNTS
+            .iter()
+            .take(num_accounts.clamp(1, ACCOUNTS.len()))
+            .map(|a| a.to_string())
+            .collect();
     }
 
     /// Check if the current generation has reached the timeout
@@ -323,9 +427,15 @@ impl Env {
         type_matched
             .into_iter()
             .filter(|id: &Identifier| self.type_pool.get_type(id).is_some())
+            .filter(|id: &Identifier| !self.hidden_vars.contains(id))
             .collect()
     }
 
+    /// Return the list of module constants of type `typ` accessible in the given scope
+    pub fn constants(&self, scope: &Scope, typ: Option<&Type>) -> Vec<Identifier> {
+        self.get_identifiers_all(typ, Some(IDKind::Constant), Some(scope))
+    }
+
     /// Return the list of live variables of type `typ` in the given scope
     pub fn live_variables(&self, scope: &Scope, typ: Option<&Type>) -> Vec<Identifier> {
         let ids = self.get_identifiers(typ, Some(IDKind::Var), Some(scope));
@@ -356,4 +466,130 @@ impl Env {
                 .select_once(u)
                 .unwrap()
     }
+
+    #[inline]
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+    }
+
+    #[inline]
+    pub fn exit_loop(&mut self) {
+        self.loop_depth -= 1;
+    }
+
+    /// Check if we are generating code inside a loop body,
+    /// i.e. whether `break` and `continue` can be used
+    #[inline]
+    pub fn in_loop(&self) -> bool {
+        self.loop_depth > 0
+    }
+
+    #[inline]
+    pub fn reached_loop_depth_limit(&self) -> bool {
+        self.loop_depth >= self.config.max_loop_depth
+    }
+
+    #[inline]
+    pub fn enter_lambda(&mut self) {
+        self.lambda_loop_depths.push(self.loop_depth);
+        self.loop_depth = 0;
+    }
+
+    #[inline]
+    pub fn exit_lambda(&mut self) {
+        self.loop_depth = self.lambda_loop_depths.pop().unwrap();
+    }
+
+    /// Check if we are generating code inside a lambda body,
+    /// where `return` is not allowed
+    #[inline]
+    pub fn in_lambda(&self) -> bool {
+        !self.lambda_loop_depths.is_empty()
+    }
+
+    pub fn hide_var(&mut self, id: &Identifier) {
+        self.hidden_vars.insert(id.clone());
+    }
+
+    pub fn unhide_var(&mut self, id: &Identifier) {
+        self.hidden_vars.remove(id);
+    }
+
+    pub fn mark_read_only(&mut self, id: &Identifier) {
+        self.read_only_vars.insert(id.clone());
+    }
+
+    pub fn is_read_only(&self, id: &Identifier) -> bool {
+        self.read_only_vars.contains(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{StructType, TypeParameters};
+
+    fn struct_type(name: &str) -> Type {
+        Type::Struct(StructType {
+            name: Identifier::new_str(name, IDKind::Struct),
+            type_parameters: TypeParameters::default(),
+        })
+    }
+
+    fn address(key: &str) -> (String, Expression) {
+        (key.to_string(), Expression::AddressLiteral(key.to_string()))
+    }
+
+    #[test]
+    fn test_storage_forgets_aliases() {
+        let s1 = struct_type("0xCAFE::Module1::Struct1");
+        let s2 = struct_type("0xCAFE::Module1::Struct2");
+        let (beef, beef_expr) = address("@0xBEEF");
+        let (cafe, cafe_expr) = address("@0xCAFE");
+
+        let mut storage = StorageModel::new();
+        storage.set_published(&s1, beef.clone(), beef_expr.clone(), true);
+        storage.set_published(&s2, beef.clone(), beef_expr.clone(), true);
+        assert_eq!(storage.is_published(&s1, &beef), Some(true));
+        assert_eq!(storage.is_published(&s1, &cafe), None);
+
+        // `@0xCAFE` may compute the same address as `@0xBEEF`, so the fact
+        // about `Struct1` under `@0xBEEF` is forgotten after the move_from
+        storage.set_published(&s1, cafe.clone(), cafe_expr, false);
+        assert_eq!(storage.is_published(&s1, &cafe), Some(false));
+        assert_eq!(storage.is_published(&s1, &beef), None);
+        // Other types are not affected
+        assert_eq!(storage.is_published(&s2, &beef), Some(true));
+        assert_eq!(storage.known_facts().len(), 2);
+
+        storage.forget_type(&s2);
+        assert_eq!(storage.is_published(&s2, &beef), None);
+        storage.forget_all();
+        assert!(storage.known_facts().is_empty());
+    }
+
+    #[test]
+    fn test_storage_reset() {
+        let s1 = struct_type("0xCAFE::Module1::Struct1");
+        let (beef, beef_expr) = address("@0xBEEF");
+        let block1 = Scope(Some("0xCAFE::Module1::function1::_block1".to_string()));
+        let block2 = Scope(Some("0xCAFE::Module1::function2::_block2".to_string()));
+
+        let mut storage = StorageModel::new();
+        assert!(!storage.is_straight_line(&block1));
+
+        storage.reset(Some(block1.clone()));
+        storage.set_published(&s1, beef.clone(), beef_expr, true);
+        assert!(storage.is_straight_line(&block1));
+        assert!(!storage.is_straight_line(&block2));
+
+        storage.reset(Some(block2.clone()));
+        assert!(storage.is_straight_line(&block2));
+        assert!(!storage.is_straight_line(&block1));
+        assert_eq!(storage.is_published(&s1, &beef), None);
+        assert!(storage.known_facts().is_empty());
+
+        storage.reset(None);
+        assert!(!storage.is_straight_line(&block2));
+    }
 }
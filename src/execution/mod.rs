@@ -12,6 +12,7 @@ use std::{
 };
 
 pub mod transactional;
+pub mod unit_test;
 
 #[derive(ValueEnum, Clone, Debug)]
 pub enum ReportFormat {
@@ -1,4 +1,5 @@
 use crate::{
+    config::GenerationConfig,
     execution::{Report, ReportFormat},
     utils::create_tmp_move_file,
 };
@@ -61,11 +62,11 @@ impl RunConfig {
         match &self.mode {
             ExecutionMode::V1Only => TestRunConfig::CompilerV1,
             ExecutionMode::V2Only => TestRunConfig::CompilerV2 {
-                language_version: LanguageVersion::V2_0,
+                language_version: LanguageVersion::V2_2,
                 v2_experiments,
             },
             ExecutionMode::V1V2Comparison => TestRunConfig::ComparisonV1V2 {
-                language_version: LanguageVersion::V2_0,
+                language_version: LanguageVersion::V2_2,
                 v2_experiments,
             },
         }
@@ -81,6 +82,16 @@ pub enum CommonRunConfig {
 }
 
 impl CommonRunConfig {
+    /// The default runs for code generated with `config`.
+    /// Compiler V1 cannot compile Move 2 code, so it is only compared
+    /// between V2 configurations.
+    pub fn default_for(config: &GenerationConfig) -> Self {
+        match config.allow_move2 {
+            true => Self::V2OptNoOpt,
+            false => Self::default(),
+        }
+    }
+
     pub fn to_run_configs(&self) -> Vec<RunConfig> {
         use CommonRunConfig::*;
         match self {
@@ -9,7 +9,8 @@ use std::{
 };
 
 const SUCCESS_MSG: &str = "Success";
-const TO_IGNORE: [&str; 14] = [
+const REFERENCE_SAFETY_MSG: &str = "... reference safety violation ...";
+pub(crate) const TO_IGNORE: [&str; 8] = [
     "EXTRANEOUS_ACQUIRES_ANNOTATION",
     "infer",
     "MAX_",
@@ -17,15 +18,30 @@ const TO_IGNORE: [&str; 14] = [
     "exceeded maximal",
     "EQUALITY_OP_TYPE_MISMATCH_ERROR",
     "unbound",
-    "dangling",
     "OUT_OF_GAS",
-    // V1 vector bugs
+];
+/// Verifier errors on the bytecode from V1 caused by known V1 bugs with vectors.
+/// They are only ignored on the V1 side of a diff run, and only if V2 finds no
+/// reference safety violation in the same code.
+const V1_BORROW_BUGS: [&str; 5] = [
     "READREF_EXISTS_MUTABLE_BORROW_ERROR",
     "CALL_BORROWED_MUTABLE_REFERENCE_ERRO",
     "VEC_UPDATE_EXISTS_MUTABLE_BORROW_ERROR",
     "BORROWLOC_EXISTS_BORROW_ERROR",
     "VEC_BORROW_ELEMENT_EXISTS_MUTABLE_BORROW_ERROR",
-    // end V1 vector bugs
+];
+/// Errors from the reference safety analyses of the compilers and the verifier.
+/// Their wording and granularity differ, so only whether a program is
+/// rejected for reference safety is compared.
+const REFERENCE_SAFETY_ERRORS: [&str; 8] = [
+    "_BORROW_ERROR",
+    "BORROWED_MUTABLE_REFERENCE",
+    "UNSAFE_RET",
+    "dangling",
+    "Invalid return of reference",
+    "Invalid usage of reference",
+    "cannot return a reference",
+    "still mutably borrowed",
 ];
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Hash)]
@@ -145,9 +161,14 @@ impl TransactionalResultBuilder {
             }
             if is_diff {
                 let (v1_log, v2_log) = Self::split_diff_log(&run_log);
+                if Self::is_v1_borrow_bug(&v1_log, &v2_log) {
+                    return TransactionalResult::success();
+                }
                 log_strings.push(v1_log);
                 log_strings.push(v2_log);
             } else {
+                // Runs without V1 are compared as is, so that reference safety
+                // errors reported by V2 are not hidden
                 log_strings.push(run_log);
             }
         }
@@ -166,6 +187,13 @@ impl TransactionalResultBuilder {
         result
     }
 
+    /// Check if V1 hits one of its known borrow bugs while V2 finds
+    /// the code safe, in which case the difference is expected.
+    fn is_v1_borrow_bug(v1_log: &str, v2_log: &str) -> bool {
+        V1_BORROW_BUGS.iter().any(|bug| v1_log.contains(bug))
+            && !REFERENCE_SAFETY_ERRORS.iter().any(|e| v2_log.contains(e))
+    }
+
     fn split_diff_log(log: &str) -> (String, String) {
         let mut left = vec![];
         let mut right = vec![];
@@ -260,6 +288,9 @@ static SOME_PAT: Lazy<Regex> = Lazy::new(|| Regex::new(r"Some\([^\)]+\)").unwrap
 
 static ERROR_CODE_PAT: Lazy<Regex> = Lazy::new(|| Regex::new(r"`([^`]*)`").unwrap());
 
+static FUNCTION_INDEX_PAT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"FunctionDefinitionIndex\((\d+)\)").unwrap());
+
 impl ResultChunk {
     fn log_to_chunck(log: &[String]) -> Vec<ResultChunk> {
         let mut chunks = vec![];
@@ -286,6 +317,8 @@ impl ResultChunk {
         chunks
             .iter_mut()
             .for_each(|e| e.canonical = e.get_canonicalized_msg());
+        // The analyses may report one violation as several errors
+        chunks.dedup_by(|a, b| a.canonical == REFERENCE_SAFETY_MSG && b.canonical == a.canonical);
         chunks
     }
 
@@ -299,6 +332,14 @@ impl ResultChunk {
 
         let full = &self.original;
 
+        if top.contains("major_status: ABORTED") {
+            return self.get_abort_msg();
+        }
+
+        if REFERENCE_SAFETY_ERRORS.iter().any(|e| full.contains(e)) {
+            return REFERENCE_SAFETY_MSG.to_string();
+        }
+
         if top.contains("major_status") {
             return top
                 .replace("major_status: ", "error_code: ")
@@ -365,6 +406,43 @@ impl ResultChunk {
         let replaced = SOME_PAT.replace_all(&replaced, "[some value]").to_string();
         replaced
     }
+
+    /// Canonicalize an abort with its abort code, module and function, so that runs
+    /// aborting with different codes or in different functions are told apart.
+    ///
+    /// The code offset is left out: the compilers and their optimizations emit
+    /// different bytecode for the same abort. It stays in the original output.
+    ///
+    /// The VM error is formatted as:
+    /// ```text
+    /// VMError {
+    ///     major_status: ABORTED,
+    ///     sub_status: Some(42),
+    ///     location: 0xcafe::Module0,
+    ///     indices: [],
+    ///     offsets: [(FunctionDefinitionIndex(3), 12)],
+    /// }
+    /// ```
+    fn get_abort_msg(&self) -> String {
+        let field = |name: &str| {
+            self.lines
+                .iter()
+                .find_map(|l| l.strip_prefix(name))
+                .map(|v| v.trim().trim_end_matches(',').to_string())
+                .unwrap_or_default()
+        };
+        let offsets = field("offsets:");
+        let function = FUNCTION_INDEX_PAT
+            .captures(&offsets)
+            .map(|caps| caps[1].to_string())
+            .unwrap_or_default();
+        format!(
+            "error_code: ABORTED sub_status: {} location: {} function: {}",
+            field("sub_status:"),
+            field("location:"),
+            function
+        )
+    }
 }
 
 impl Display for TransactionalResult {
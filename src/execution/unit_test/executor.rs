@@ -0,0 +1,72 @@
+use super::result::UnitTestResultBuilder;
+use crate::{
+    execution::{
+        unit_test::{UnitTestInput, UnitTestResult},
+        Executor,
+    },
+    utils::{create_compiler_config_v1, create_compiler_config_v2},
+};
+#[cfg(feature = "git_deps")]
+use move_cli::base::test::run_move_unit_tests;
+#[cfg(feature = "local_deps")]
+use move_cli_local::base::test::run_move_unit_tests;
+#[cfg(feature = "git_deps")]
+use move_core_types::{account_address::AccountAddress, effects::ChangeSet};
+#[cfg(feature = "local_deps")]
+use move_core_types_local::{account_address::AccountAddress, effects::ChangeSet};
+#[cfg(feature = "git_deps")]
+use move_stdlib::natives::{all_natives, GasParameters};
+#[cfg(feature = "local_deps")]
+use move_stdlib_local::natives::{all_natives, GasParameters};
+#[cfg(feature = "git_deps")]
+use move_unit_test::UnitTestingConfig;
+#[cfg(feature = "local_deps")]
+use move_unit_test_local::UnitTestingConfig;
+use std::time::Instant;
+
+/// Maximum number of instructions a single unit test can execute
+const INSTRUCTION_BOUND: u64 = 100_000;
+
+/// Runs the `#[test]` functions of a package with the V2 compiler,
+/// and also with the V1 compiler if the input asks for the comparison
+#[derive(Default)]
+pub struct UnitTestExecutor;
+
+impl Executor<UnitTestResult> for UnitTestExecutor {
+    type Input = UnitTestInput;
+
+    fn execute_one(&self, input: &UnitTestInput) -> UnitTestResult {
+        let (path, dir) = input.get_package_path();
+
+        let mut result_builder = UnitTestResultBuilder::new();
+
+        let start = Instant::now();
+        let mut build_configs = vec![];
+        if input.compare_v1 {
+            build_configs.push(create_compiler_config_v1());
+        }
+        build_configs.push(create_compiler_config_v2());
+
+        for build_config in build_configs {
+            let natives = all_natives(AccountAddress::ONE, GasParameters::zeros()).collect();
+            let mut output = Vec::new();
+            let result = run_move_unit_tests(
+                &path,
+                build_config,
+                UnitTestingConfig::default_with_bound(Some(INSTRUCTION_BOUND)),
+                natives,
+                ChangeSet::new(),
+                None,
+                None,
+                false,
+                &mut output,
+            );
+            let log = String::from_utf8_lossy(&output).to_string();
+            result_builder.add_result(result.map(|_| ()), log);
+        }
+        let duration = start.elapsed();
+        let output = result_builder.build(duration);
+        dir.close().unwrap();
+        output
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    execution::{Report, ReportFormat},
+    utils::create_tmp_move_package,
+};
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[derive(Clone)]
+pub struct UnitTestInput {
+    pub file: Option<PathBuf>,
+    pub code: String,
+    /// Also run the tests with the V1 compiler and compare the outcomes.
+    /// Should be off for Move 2 code, which V1 cannot compile.
+    pub compare_v1: bool,
+}
+
+impl UnitTestInput {
+    pub fn new(code: &str, file: Option<PathBuf>) -> Self {
+        Self {
+            file,
+            code: code.to_string(),
+            compare_v1: true,
+        }
+    }
+
+    pub fn set_compare_v1(&mut self, compare_v1: bool) -> &mut Self {
+        self.compare_v1 = compare_v1;
+        self
+    }
+
+    pub fn load_code_from_file(file: PathBuf) -> Self {
+        let code = std::fs::read_to_string(&file).unwrap();
+        Self::new(&code, Some(file))
+    }
+
+    /// Write the code into a temporary package so that the unit test
+    /// runner can build it
+    pub fn get_package_path(&self) -> (PathBuf, TempDir) {
+        create_tmp_move_package(self.code.clone())
+    }
+}
+
+impl Report for UnitTestInput {
+    fn clean(&mut self) {
+        self.code = "".to_string();
+    }
+
+    fn to_report(&self, _format: &ReportFormat) -> String {
+        match &self.file {
+            Some(file) => format!("{}", file.to_string_lossy()),
+            None => "".to_string(),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod executor;
+pub mod input;
+pub mod result;
+
+pub use executor::UnitTestExecutor;
+pub use input::UnitTestInput;
+pub use result::UnitTestResult;
@@ -0,0 +1,281 @@
+use crate::execution::{
+    transactional::result::{ResultStatus, TO_IGNORE},
+    ExecutionResult, Report, ReportFormat, ResultCompareMode,
+};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    panic::PanicHookInfo,
+    time::Duration,
+};
+
+/// Matches a line reporting the outcome of one test, e.g.
+/// `[ PASS    ] 0xcafe::Module0::function0_test_0`
+static OUTCOME_PAT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\[\s*(PASS|FAIL|TIMEOUT)\s*\]\s+(\S+)").unwrap());
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Hash)]
+pub struct UnitTestResult {
+    pub log: String,
+    pub status: ResultStatus,
+    /// The outcome of each test for each run (V1 is one run if enabled, V2 is another run).
+    /// `None` means the package failed to build.
+    pub outcomes: Vec<Option<BTreeMap<String, String>>>,
+    pub duration: Duration,
+}
+
+#[derive(Default)]
+pub struct UnitTestResultBuilder {
+    /// The result and the output of the test runner of each run
+    results: Vec<(Result<()>, String)>,
+}
+
+impl UnitTestResultBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_result(&mut self, res: Result<()>, log: String) -> &mut Self {
+        self.results.push((res, log));
+        self
+    }
+
+    pub fn build(self, duration: Duration) -> UnitTestResult {
+        let mut result = UnitTestResult {
+            duration,
+            ..Default::default()
+        };
+        for (i, (res, log)) in self.results.into_iter().enumerate() {
+            result.log.push_str(&format!("Log from run #{}\n", i + 1));
+            result.log.push_str(&log);
+            let outcome = match res {
+                Ok(_) => Some(Self::parse_outcomes(&log)),
+                Err(e) => {
+                    let err = format!("{:?}", e);
+                    if TO_IGNORE.iter().any(|ignore| err.contains(ignore)) {
+                        return UnitTestResult::success();
+                    }
+                    result.log.push_str(&err);
+                    None
+                },
+            };
+            result.outcomes.push(outcome);
+        }
+        // With a single run there is nothing to compare against,
+        // so only a build failure is reported
+        let consistent = match result.outcomes.len() {
+            1 => result.outcomes[0].is_some(),
+            _ => result.outcomes.windows(2).all(|w| w[0] == w[1]),
+        };
+        result.status = match consistent {
+            true => ResultStatus::Success,
+            false => ResultStatus::Failure,
+        };
+        result
+    }
+
+    fn parse_outcomes(log: &str) -> BTreeMap<String, String> {
+        log.lines()
+            .filter_map(|l| OUTCOME_PAT.captures(l.trim()))
+            .map(|caps| (caps[2].to_string(), caps[1].to_string()))
+            .collect()
+    }
+}
+
+impl UnitTestResult {
+    pub fn success() -> Self {
+        Self {
+            log: "Success".to_string(),
+            status: ResultStatus::Success,
+            ..Default::default()
+        }
+    }
+
+    /// Collect the outcomes of the tests that differ between the runs,
+    /// with the test names left out so that results of different inputs compare
+    fn diverging_tests(&self) -> Vec<Vec<Option<String>>> {
+        let names = self
+            .outcomes
+            .iter()
+            .flatten()
+            .flat_map(|tests| tests.keys().cloned())
+            .collect::<BTreeSet<String>>();
+        let mut diverging = names
+            .iter()
+            .map(|name| {
+                self.outcomes
+                    .iter()
+                    .map(|tests| tests.as_ref().and_then(|t| t.get(name).cloned()))
+                    .collect::<Vec<Option<String>>>()
+            })
+            .filter(|outcomes| outcomes.windows(2).any(|w| w[0] != w[1]))
+            .collect::<Vec<_>>();
+        diverging.sort();
+        diverging.dedup();
+        diverging
+    }
+}
+
+impl Display for UnitTestResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if matches!(self.status, ResultStatus::Panic) {
+            writeln!(f, "{}", self.log)?;
+            return Ok(());
+        }
+        for (i, outcome) in self.outcomes.iter().enumerate() {
+            writeln!(f, "\n#{} output:", i + 1)?;
+            match outcome {
+                Some(tests) => {
+                    for (name, outcome) in tests.iter() {
+                        writeln!(f, "{}: {}", name, outcome)?;
+                    }
+                },
+                None => writeln!(f, "failed to build")?,
+            }
+        }
+        writeln!(f, "Status: {:?}", self.status)?;
+        writeln!(f, "Duration: {:?}", self.duration)?;
+        Ok(())
+    }
+}
+
+impl ExecutionResult for UnitTestResult {
+    fn from_panic(panic: &PanicHookInfo) -> Self {
+        let log = format!("panicked: {}", panic.location().unwrap());
+        Self {
+            log,
+            status: ResultStatus::Panic,
+            ..Default::default()
+        }
+    }
+
+    fn is_bug(&self) -> bool {
+        self.status != ResultStatus::Success
+    }
+
+    fn similar(&self, other: &Self, mode: &ResultCompareMode) -> bool {
+        if matches!(self.status, ResultStatus::Panic) && matches!(other.status, ResultStatus::Panic)
+        {
+            let left_loc = self.log.lines().next().unwrap();
+            let right_loc = other.log.lines().next().unwrap();
+            return left_loc == right_loc;
+        }
+        match mode {
+            ResultCompareMode::Exact => self.outcomes == other.outcomes,
+            // Only the tests with different outcomes between the runs matter
+            ResultCompareMode::SameError => self.diverging_tests() == other.diverging_tests(),
+        }
+    }
+}
+
+impl Report for UnitTestResult {
+    fn clean(&mut self) {
+        if self.status != ResultStatus::Panic {
+            self.log.clear();
+        }
+    }
+
+    fn to_report(&self, format: &ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.to_string(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_LOG: &str = r#"INCLUDING DEPENDENCY MoveStdlib
+BUILDING MoveSmith
+Running Move unit tests
+[ PASS    ] 0xcafe::Module0::function0_test_0
+[ FAIL    ] 0xcafe::Module0::function1_test_0
+[ TIMEOUT ] 0xcafe::Module1::function2_test_1
+Test failures:
+
+Failures in 0xcafe::Module0:
+
+┌── function1_test_0 ──────
+│ error[E11001]: test failure
+│    ┌── sources/main.move:42:9 ───
+│ Test was not expected to error, but it aborted with code 1 originating in the module 0xcafe::Module0
+└──────────────────
+
+Test result: FAILED. Total tests: 3; passed: 1; failed: 2
+"#;
+
+    #[test]
+    fn test_outcome_pattern() {
+        let caps = OUTCOME_PAT
+            .captures("[ PASS    ] 0xcafe::Module0::function0_test_0")
+            .unwrap();
+        assert_eq!(&caps[1], "PASS");
+        assert_eq!(&caps[2], "0xcafe::Module0::function0_test_0");
+
+        let caps = OUTCOME_PAT
+            .captures("[ TIMEOUT ] 0xcafe::Module1::function2_test_1")
+            .unwrap();
+        assert_eq!(&caps[1], "TIMEOUT");
+        assert_eq!(&caps[2], "0xcafe::Module1::function2_test_1");
+
+        assert!(OUTCOME_PAT.captures("Running Move unit tests").is_none());
+        assert!(OUTCOME_PAT
+            .captures("Test result: FAILED. Total tests: 3; passed: 1; failed: 2")
+            .is_none());
+        assert!(OUTCOME_PAT
+            .captures("[ SKIP ] 0xcafe::Module0::f")
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_outcomes() {
+        let outcomes = UnitTestResultBuilder::parse_outcomes(TEST_LOG);
+        let expected = BTreeMap::from([
+            (
+                "0xcafe::Module0::function0_test_0".to_string(),
+                "PASS".to_string(),
+            ),
+            (
+                "0xcafe::Module0::function1_test_0".to_string(),
+                "FAIL".to_string(),
+            ),
+            (
+                "0xcafe::Module1::function2_test_1".to_string(),
+                "TIMEOUT".to_string(),
+            ),
+        ]);
+        assert_eq!(outcomes, expected);
+        assert!(UnitTestResultBuilder::parse_outcomes("").is_empty());
+    }
+
+    #[test]
+    fn test_build_compares_outcomes() {
+        let mut builder = UnitTestResultBuilder::new();
+        builder.add_result(Ok(()), TEST_LOG.to_string());
+        builder.add_result(Ok(()), TEST_LOG.to_string());
+        let result = builder.build(Duration::ZERO);
+        assert_eq!(result.status, ResultStatus::Success);
+
+        let mut builder = UnitTestResultBuilder::new();
+        builder.add_result(Ok(()), TEST_LOG.to_string());
+        builder.add_result(Ok(()), TEST_LOG.replace("[ FAIL    ]", "[ PASS    ]"));
+        let result = builder.build(Duration::ZERO);
+        assert_eq!(result.status, ResultStatus::Failure);
+        assert_eq!(result.diverging_tests(), vec![vec![
+            Some("FAIL".to_string()),
+            Some("PASS".to_string())
+        ]]);
+
+        let mut builder = UnitTestResultBuilder::new();
+        builder.add_result(Err(anyhow::anyhow!("failed to compile")), "".to_string());
+        let result = builder.build(Duration::ZERO);
+        assert_eq!(result.status, ResultStatus::Failure);
+        assert_eq!(result.outcomes, vec![None]);
+    }
+}
@@ -12,6 +12,7 @@ pub mod execution;
 pub mod move_smith;
 pub mod names;
 pub mod selection;
+pub mod stdlib;
 pub mod types;
 pub mod utils;
 
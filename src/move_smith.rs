@@ -16,6 +16,7 @@
 //! 2. Fill in the details of the generated elements.
 //!     - Fill in struct fields
 //!     - Fill in function bodies
+//!     - Generate spec blocks for the module, its structs and functions
 
 use crate::{
     ast::*,
@@ -23,9 +24,10 @@ use crate::{
     config::GenerationConfig,
     env::Env,
     names::{Identifier, IdentifierKind as IDKinds, Scope, ROOT_SCOPE},
+    stdlib::{StdFunction, StdType, ABORTING_CALLS, STDLIB},
     types::{
-        Ability, HasType, StructType, StructTypeConcrete, Type, TypeArgs, TypeParameter,
-        TypeParameters,
+        Ability, FunctionType, HasType, LambdaType, StructType, StructTypeConcrete, Type, TypeArgs,
+        TypeParameter, TypeParameters,
     },
     utils::{choose_idx_weighted, choose_item_weighted},
 };
@@ -42,21 +44,58 @@ use std::{
 pub struct MoveSmith {
     // The output code
     modules: Vec<RefCell<Module>>,
-    script: Option<Script>,
-    runs: RefCell<Vec<Identifier>>,
+    scripts: Vec<Script>,
+    runs: RefCell<Vec<Run>>,
+    scenarios: RefCell<Vec<Scenario>>,
+    // One abort code constant for each assert site, declared in the modules at the end
+    error_codes: RefCell<Vec<Constant>>,
 
     // Bookkeeping
     env: RefCell<Env>,
 }
 
+/// What a specification expression being generated can refer to.
+/// All integer types are `num` in specifications, so integer values are
+/// tracked with the representative type `u64`.
+#[derive(Debug, Clone)]
+struct SpecContext {
+    /// The scope for the names of quantified variables
+    scope: Scope,
+    /// Values that can be used as they are, e.g. parameters, constants or `result`
+    values: Vec<(SpecExpression, Type)>,
+    /// Values whose pre-state can be read with `old()`, i.e. `&mut` parameters
+    old_values: Vec<(SpecExpression, Type)>,
+    /// Whether `old()` can be used, which is only the case in `ensures`
+    allow_old: bool,
+    /// Resources of the module that can be read with `global<T>(addr)`
+    resources: Vec<StructDefinition>,
+    /// Spec helper functions that can be called
+    helpers: Vec<SpecFunction>,
+}
+
+impl SpecContext {
+    fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            values: Vec::new(),
+            old_values: Vec::new(),
+            allow_old: false,
+            resources: Vec::new(),
+            helpers: Vec::new(),
+        }
+    }
+}
+
 impl MoveSmith {
     /// Create a new MoveSmith instance with the given configuration.
     pub fn new(config: &GenerationConfig) -> Self {
         let env = Env::new(config);
         Self {
             modules: Vec::new(),
-            script: None,
+            scripts: Vec::new(),
             runs: RefCell::new(Vec::new()),
+            scenarios: RefCell::new(Vec::new()),
+            error_codes: RefCell::new(Vec::new()),
             env: RefCell::new(env),
         }
     }
@@ -69,6 +108,11 @@ impl MoveSmith {
         self.env.borrow_mut()
     }
 
+    /// Check if constructs only supported by Move 2 can be generated
+    fn allow_move2(&self) -> bool {
+        self.env().config.allow_move2
+    }
+
     /// Get the generated compile unit.
     pub fn get_compile_unit(&self) -> CompileUnit {
         let modules = self
@@ -79,11 +123,10 @@ impl MoveSmith {
         let runs = self.runs.borrow().clone();
         CompileUnit {
             modules,
-            scripts: match &self.script {
-                Some(s) => vec![s.clone()],
-                None => Vec::new(),
-            },
+            scripts: self.scripts.clone(),
             runs,
+            scenarios: self.scenarios.borrow().clone(),
+            accounts: self.env().accounts.clone(),
         }
     }
 
@@ -108,27 +151,96 @@ impl MoveSmith {
         }
         info!("Done fill in skeletons");
 
-        // Note: disable script generation for now since intermediate states are not compared
-        // we use `//# run`` to execute the functions instead
-        self.script = None;
-
         self.post_process(u)?;
 
+        // Scripts can only call functions once their bodies and visibility are final
+        let num_scripts = self.env().config.num_scripts.select(u)?;
+        trace!("NUM: generating {} scripts", num_scripts);
+        for _ in 0..num_scripts {
+            let script = self.generate_script(u)?;
+            self.scripts.push(script);
+        }
+        info!("Done generating scripts");
+
         for m in self.modules.iter() {
+            self.add_unit_tests(u, m)?;
             self.add_runners(u, m)?;
+            self.add_scenarios(u, m)?;
         }
 
+        self.declare_error_codes();
         Ok(())
     }
 
+    /// Declare the abort codes used by the functions of a module as its constants.
+    /// The codes used in scripts are emitted as literals.
+    fn declare_error_codes(&self) {
+        for code in self.error_codes.take() {
+            let module_scope = self.env().id_pool.get_parent_scope_of(&code.name).unwrap();
+            let module_name = module_scope.to_pieces().last().cloned();
+            if let Some(m) = self
+                .modules
+                .iter()
+                .find(|m| Some(&m.borrow().name.name) == module_name.as_ref())
+            {
+                m.borrow_mut().constants.push(code);
+            }
+        }
+    }
+
     /// Post process the generated Move module to fix simple errors
     pub fn post_process(&self, u: &mut Unstructured) -> Result<()> {
         for m in self.modules.iter() {
             self.post_process_module(u, m)?;
         }
+        self.post_process_friends();
         Ok(())
     }
 
+    /// Declare the modules calling `public(friend)` functions of another module
+    /// as friends of that module.
+    pub fn post_process_friends(&self) {
+        for caller in self.modules.iter() {
+            let caller_name = caller.borrow().name.clone();
+
+            let mut callees = BTreeSet::new();
+            for f in caller.borrow().functions.iter() {
+                let fref = f.borrow();
+                let call_exprs = fref.all_exprs(Some(|e| matches!(e, Expression::FunctionCall(_))));
+                for ce in call_exprs {
+                    if let Expression::FunctionCall(c) = ce {
+                        callees.extend(self.resolve_callee(c));
+                    }
+                }
+            }
+
+            for m in self.modules.iter() {
+                if m.borrow().name == caller_name {
+                    continue;
+                }
+                let calls_friend_func = m.borrow().functions.iter().any(|f| {
+                    let fref = f.borrow();
+                    fref.visibility == Visibility::Friend && callees.contains(&fref.signature.name)
+                });
+                if calls_friend_func && !m.borrow().friends.contains(&caller_name) {
+                    m.borrow_mut().friends.push(caller_name.clone());
+                }
+            }
+        }
+    }
+
+    /// Resolve the generated function called by `call` through the id_pool.
+    ///
+    /// Function names are unique across modules, so a call to another module,
+    /// e.g. `Module1::function3`, is resolved by the name of the function.
+    /// Returns `None` for calls to the stdlib, the prologue, lambdas and function values.
+    fn resolve_callee(&self, call: &FunctionCall) -> Option<Identifier> {
+        let name = call.name.name.rsplit("::").next()?;
+        let callee = Identifier::new_str(name, IDKinds::Function);
+        self.env().id_pool.get_parent_scope_of(&callee)?;
+        Some(callee)
+    }
+
     pub fn post_process_module(
         &self,
         u: &mut Unstructured,
@@ -254,33 +366,149 @@ impl MoveSmith {
         Ok(())
     }
 
-    /// Generate a script that calls functions from the generated modules.
-    #[allow(dead_code)]
+    /// Generate a script that calls public functions, including `public entry`
+    /// ones, from the generated modules and records their results.
+    ///
+    /// The `main` function takes a signer and primitive arguments, which can be
+    /// used as arguments of the calls. Like runners, the calls only use simple
+    /// expressions to reduce input consumption.
     fn generate_script(&self, u: &mut Unstructured) -> Result<Script> {
-        let mut script = Script { main: Vec::new() };
+        let (_, scope) = self.get_next_identifier(IDKinds::Script, &ROOT_SCOPE);
+        self.env_mut().curr_func_signature = None;
+        self.env_mut().expr_depth.set_max_depth(0);
 
-        let mut all_funcs: Vec<RefCell<Function>> = Vec::new();
-        for m in self.modules.iter() {
-            for f in m.borrow().functions.iter() {
-                all_funcs.push(f.clone());
-            }
+        let mut parameters = vec![(self.env().type_pool.get_signer_var(), Type::Signer)];
+        let mut stmts = vec![Statement::Decl(Declaration {
+            names: vec![self.env().type_pool.get_signer_ref_var()],
+            typs: vec![Type::Ref(Box::new(Type::Signer))],
+            value: Some(Expression::Reference(Box::new(Expression::Variable(
+                VariableAccess {
+                    name: self.env().type_pool.get_signer_var(),
+                    copy: false,
+                },
+            )))),
+            emit_type: false,
+        })];
+
+        // Signer parameters must precede the other parameters of a script
+        let num_signers = self
+            .env()
+            .config
+            .num_signer_params_in_func
+            .select(u)?
+            .min(self.env().accounts.len() - 1);
+        for _ in 0..num_signers {
+            let (signer, _) = self.get_next_identifier(IDKinds::Var, &scope);
+            self.env_mut()
+                .type_pool
+                .insert_mapping(&signer, &Type::Signer);
+            parameters.push((signer.clone(), Type::Signer));
+
+            let (signer_ref, _) = self.get_next_identifier(IDKinds::Var, &scope);
+            let typ = Type::Ref(Box::new(Type::Signer));
+            self.env_mut().type_pool.insert_mapping(&signer_ref, &typ);
+            self.env_mut().live_vars.mark_alive(&scope, &signer_ref);
+            stmts.push(Statement::Decl(Declaration {
+                names: vec![signer_ref],
+                typs: vec![typ],
+                value: Some(Expression::Reference(Box::new(Expression::Variable(
+                    VariableAccess {
+                        name: signer,
+                        copy: false,
+                    },
+                )))),
+                emit_type: true,
+            }));
+        }
+        let signers = self.choose_signers(u, parameters.len())?;
+
+        let mut args = Vec::new();
+        let num_params = self.env().config.num_params_in_script.select(u)?;
+        trace!("NUM: generating {} parameters for script", num_params);
+        for _ in 0..num_params {
+            let (name, _) = self.get_next_identifier(IDKinds::Var, &scope);
+            let typ = self.get_random_type(u, &scope, true, false, false, false, false)?;
+            self.env_mut().type_pool.insert_mapping(&name, &typ);
+            self.env_mut().live_vars.mark_alive(&scope, &name);
+            args.push(self.generate_expression_of_type(u, &scope, &typ, false, false)?);
+            parameters.push((name, typ));
         }
-        let num_calls = self.env().config.num_calls_in_script.select(u)?;
+
+        let callable = self
+            .modules
+            .iter()
+            .flat_map(|m| m.borrow().functions.clone())
+            .filter(|f| self.is_callable_from_script(&f.borrow()))
+            .map(|f| f.borrow().signature.clone())
+            .collect::<Vec<FunctionSignature>>();
+        let num_calls = match callable.is_empty() {
+            true => 0,
+            false => self.env().config.num_calls_in_script.select(u)?,
+        };
         trace!("NUM: generating {} calls in the script", num_calls);
         for _ in 0..num_calls {
-            let func = u.choose(&all_funcs)?;
-            let mut call = self.generate_call_to_function(
-                u,
-                &ROOT_SCOPE,
-                &func.borrow().signature,
-                None,
-                false,
-            )?;
-            call.name = self.env().id_pool.flatten_access(&call.name);
-            script.main.push(call);
+            let func = u.choose(&callable)?;
+            let mut call = self.generate_call_to_function(u, &scope, func, None, true)?;
+            call.name = self.env().id_pool.flatten_access(&func.name);
+
+            let ret_typ = match &func.return_type {
+                Some(typ) => self.instantiate_type(typ, &func.type_parameters, &call.type_args),
+                None => {
+                    stmts.push(Statement::Expr(Expression::FunctionCall(call)));
+                    continue;
+                },
+            };
+            let (name, _) = self.get_next_identifier(IDKinds::Var, &scope);
+            self.env_mut().type_pool.insert_mapping(&name, &ret_typ);
+            // Returned references are only recorded, so that they
+            // cannot outlive the arguments they borrow from
+            if !matches!(ret_typ, Type::Ref(_) | Type::MutRef(_)) {
+                self.env_mut().live_vars.mark_alive(&scope, &name);
+            }
+            let record = self.generate_record_value_expr(&name);
+            stmts.push(Statement::Decl(Declaration {
+                names: vec![name],
+                typs: vec![ret_typ],
+                value: Some(Expression::FunctionCall(call)),
+                emit_type: true,
+            }));
+            stmts.extend(record.map(Statement::Expr));
         }
+        self.env_mut().expr_depth.reset_max_depth();
 
-        Ok(script)
+        let main = Function {
+            visibility: Visibility::Private,
+            is_entry: false,
+            signature: FunctionSignature {
+                inline: false,
+                type_parameters: TypeParameters::default(),
+                name: Identifier::new_str("main", IDKinds::Function),
+                parameters,
+                return_type: None,
+                fuel: None,
+                acquires: BTreeSet::new(),
+            },
+            body: Some(Block {
+                name: Identifier::new_str("_block_script", IDKinds::Block),
+                stmts,
+                return_expr: None,
+            }),
+        };
+        Ok(Script {
+            uses: vec![
+                Use {
+                    address: "0x1".to_string(),
+                    module: Identifier::new_str("signer", IDKinds::Module),
+                },
+                Use {
+                    address: "0xCAFE".to_string(),
+                    module: Identifier::new_str("FuzzStore::record_value", IDKinds::Function),
+                },
+            ],
+            main,
+            args,
+            signers,
+        })
     }
 
     /// Generate a module skeleton with only struct and function skeletions.
@@ -293,7 +521,9 @@ impl MoveSmith {
         let num_structs = self.env().config.num_structs_in_module.select(u)?;
         trace!("NUM: generating {} struct skeletons", num_structs);
         for _ in 0..num_structs {
-            structs.push(RefCell::new(self.generate_struct_skeleton(u, &scope)?));
+            structs.push(RefCell::new(
+                self.generate_struct_skeleton(u, &scope, false)?,
+            ));
         }
 
         // Generate a struct with all abilities to avoid having no type to choose for some type parameters
@@ -307,38 +537,114 @@ impl MoveSmith {
             name: struct_name,
             abilities: Vec::from(Ability::ALL),
             type_parameters: TypeParameters::default(),
+            positional: false,
             fields: Vec::new(),
         }));
+
+        // Structs without copy and drop, whose values must be consumed exactly once
+        let num_linear_structs = self.env().config.num_linear_structs_in_module.select(u)?;
+        trace!(
+            "NUM: generating {} linear struct skeletons",
+            num_linear_structs
+        );
+        for _ in 0..num_linear_structs {
+            structs.push(RefCell::new(
+                self.generate_struct_skeleton(u, &scope, true)?,
+            ));
+        }
         info!("Done generating struct skeletons");
 
+        // Enum variants only have primitive fields, so enums are fully
+        // generated here and are ready to use in function bodies.
+        // Enums are only supported by Move 2. Without them, neither `match`
+        // nor `is` can be generated.
+        let mut enums = Vec::new();
+        let num_enums = match self.allow_move2() {
+            true => self.env().config.num_enums_in_module.select(u)?,
+            false => 0,
+        };
+        trace!("NUM: generating {} enums", num_enums);
+        for _ in 0..num_enums {
+            enums.push(RefCell::new(self.generate_enum(u, &scope)?));
+        }
+        info!("Done generating enums");
+
+        let mut constants = vec![Constant {
+            name: self.env().type_pool.get_address_var(),
+            typ: Type::Address,
+            value: Expression::AddressLiteral("@0xBEEF".to_string()),
+        }];
+        let num_constants = self.env().config.num_constants_in_module.select(u)?;
+        trace!("NUM: generating {} constants", num_constants);
+        for _ in 0..num_constants {
+            constants.push(self.generate_constant(u, &scope)?);
+        }
+        info!("Done generating constants");
+
         // Function signatures
+        let linear_structs = structs
+            .iter()
+            .map(|s| s.borrow())
+            .filter(|s| !s.abilities.contains(&Ability::Copy))
+            .filter(|s| s.type_parameters.type_parameters.is_empty())
+            .map(|s| s.get_type())
+            .collect::<Vec<Type>>();
+        let ref_structs = structs
+            .iter()
+            .map(|s| s.borrow())
+            .filter(|s| s.abilities.contains(&Ability::Copy))
+            .filter(|s| s.type_parameters.type_parameters.is_empty())
+            .map(|s| s.get_type())
+            .collect::<Vec<Type>>();
         let mut functions = Vec::new();
         let num_funcs = self.env().config.num_functions_in_module.select(u)?;
         trace!("NUM: generating {} function skeletons", num_funcs);
         for _ in 0..num_funcs {
-            functions.push(RefCell::new(self.generate_function_skeleton(u, &scope)?));
+            functions.push(RefCell::new(self.generate_function_skeleton(
+                u,
+                &scope,
+                &linear_structs,
+                &ref_structs,
+            )?));
         }
         info!("Done generating function skeletons");
 
-        Ok(Module {
-            uses: vec![
-                Use {
-                    address: "0x1".to_string(),
-                    module: Identifier::new_str("vector", IDKinds::Module),
-                },
-                Use {
+        // A module can depend on any module generated before it,
+        // so the dependencies always form a DAG
+        let mut uses = vec![
+            Use {
+                address: "0x1".to_string(),
+                module: Identifier::new_str("signer", IDKinds::Module),
+            },
+            Use {
+                address: "0x1".to_string(),
+                module: Identifier::new_str("vector", IDKinds::Module),
+            },
+            Use {
+                address: "0xCAFE".to_string(),
+                module: Identifier::new_str("FuzzStore::record_value", IDKinds::Function),
+            },
+        ];
+        for m in self.modules.iter() {
+            if bool::arbitrary(u)? {
+                uses.push(Use {
                     address: "0xCAFE".to_string(),
-                    module: Identifier::new_str("FuzzStore::record_value", IDKinds::Function),
-                },
-            ],
+                    module: m.borrow().name.clone(),
+                });
+            }
+        }
+
+        Ok(Module {
+            uses,
+            friends: Vec::new(),
             name,
             functions,
             structs,
-            constants: vec![Constant {
-                name: Identifier::new_str("ADDR", IDKinds::Var),
-                typ: Type::Address,
-                value: Expression::AddressLiteral("@0xBEEF".to_string()),
-            }],
+            enums,
+            constants,
+            specs: Vec::new(),
+            tests: Vec::new(),
+            accessors: Vec::new(),
         })
     }
 
@@ -353,14 +659,152 @@ impl MoveSmith {
             self.fill_struct(u, s, &scope)?;
         }
 
+        // Accessors can be called from the function bodies
+        let accessors = self.generate_accessors(u, module, &scope)?;
+        module.borrow_mut().accessors = accessors;
+
         // Generate function bodies and runners
         for f in module.borrow().functions.iter().rev() {
             self.fill_function(u, f)?;
         }
 
+        // Specifications can refer to everything generated above
+        self.generate_module_specs(u, module, &scope)?;
+
         Ok(())
     }
 
+    /// Generate accessor functions reading a copyable field of a struct through
+    /// a `self` reference, e.g. `fun function7(self: &Struct2): u64 { self.var3 }`.
+    ///
+    /// Only the non-generic structs get accessors, and only in Move 2 since
+    /// they are called with the receiver syntax.
+    /// Accessors are kept out of `functions` so that no runner, unit test
+    /// or plain call is generated for them.
+    fn generate_accessors(
+        &self,
+        u: &mut Unstructured,
+        module: &RefCell<Module>,
+        module_scope: &Scope,
+    ) -> Result<Vec<Function>> {
+        let percent = match self.allow_move2() {
+            true => self.env().config.accessor_percent.min(100),
+            false => 0,
+        };
+        let mut accessors = Vec::new();
+        for s in module.borrow().structs.iter() {
+            let st = s.borrow();
+            if !st.type_parameters.type_parameters.is_empty() {
+                continue;
+            }
+            let fields = st
+                .fields
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, typ))| {
+                    !matches!(typ, Type::Function(_))
+                        && self.derive_abilities_of_type(typ).contains(&Ability::Copy)
+                })
+                .collect::<Vec<_>>();
+            if fields.is_empty() || percent == 0 || !u.ratio(percent, 100)? {
+                continue;
+            }
+            let (idx, (field, typ)) = *u.choose(&fields)?;
+            let (name, _) = self.get_next_identifier(IDKinds::Function, module_scope);
+            let receiver = Identifier::new_str("self", IDKinds::Var);
+            let access = Expression::FieldAccess(Box::new(FieldAccess {
+                kind: FieldAccessKind::Value,
+                base: Expression::Variable(VariableAccess {
+                    name: receiver.clone(),
+                    copy: false,
+                }),
+                field: field.clone(),
+                position: st.positional.then_some(idx),
+            }));
+            accessors.push(Function {
+                signature: FunctionSignature {
+                    inline: false,
+                    type_parameters: TypeParameters::default(),
+                    name,
+                    parameters: vec![(receiver, Type::Ref(Box::new(st.get_type())))],
+                    return_type: Some(typ.clone()),
+                    fuel: None,
+                    acquires: BTreeSet::new(),
+                },
+                visibility: Visibility::Private,
+                is_entry: false,
+                body: Some(Block {
+                    name: Identifier::new_str("_block_accessor", IDKinds::Block),
+                    stmts: Vec::new(),
+                    return_expr: Some(access),
+                }),
+            });
+        }
+        Ok(accessors)
+    }
+
+    /// Call an accessor of the current module returning `typ` on a live variable
+    /// of the struct or a reference to it, either with the receiver syntax or
+    /// with the variable as an explicit argument.
+    /// Returns `None` if there is no such accessor or variable.
+    fn generate_accessor_call(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Result<Option<Expression>> {
+        let module_name = match parent_scope.to_pieces().get(1) {
+            Some(name) => name.clone(),
+            None => return Ok(None),
+        };
+        let module = match self
+            .modules
+            .iter()
+            .find(|m| m.borrow().name.name == module_name)
+        {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let mut calls = Vec::new();
+        for accessor in module.borrow().accessors.iter() {
+            let sig = &accessor.signature;
+            if sig.return_type.as_ref() != Some(typ) {
+                continue;
+            }
+            let st_typ = match &sig.parameters[0].1 {
+                Type::Ref(inner) => inner.as_ref().clone(),
+                _ => panic!("The receiver of an accessor must be a reference"),
+            };
+            // `&mut` receivers would need to be frozen first
+            for recv_typ in [st_typ.clone(), Type::Ref(Box::new(st_typ))] {
+                for var in self.env().live_variables(parent_scope, Some(&recv_typ)) {
+                    calls.push((sig.name.clone(), var));
+                }
+            }
+        }
+        if calls.is_empty() {
+            return Ok(None);
+        }
+        let (name, var) = u.choose(&calls)?.clone();
+        let is_ref = self.env().type_pool.get_type(&var).unwrap().is_some_ref();
+        let mut arg = Expression::Variable(VariableAccess {
+            name: var,
+            copy: false,
+        });
+        // The receiver syntax borrows a struct value implicitly,
+        // while an explicit argument must be borrowed
+        let receiver = bool::arbitrary(u)?;
+        if !receiver && !is_ref {
+            arg = Expression::Reference(Box::new(arg));
+        }
+        Ok(Some(Expression::FunctionCall(FunctionCall {
+            name,
+            type_args: TypeArgs::default(),
+            args: vec![arg],
+            receiver,
+        })))
+    }
+
     fn add_runners(&self, u: &mut Unstructured, module: &RefCell<Module>) -> Result<()> {
         trace!("Generating runners for module: {:?}", module.borrow().name);
         // For runners, we don't want complex expressions to reduce input
@@ -376,59 +820,749 @@ impl MoveSmith {
         self.env_mut().expr_depth.reset_max_depth();
 
         // Insert the runners to the module and add run tasks to the whole compile unit
-        // Each task is simply the flat name of the runner function
-        for r in all_runners.into_iter() {
+        // Each task is the flat name of the runner function and its signers
+        for (r, signers) in all_runners.into_iter() {
             let module_flat = self.env().id_pool.flatten_access(&module.borrow().name);
 
             let runner_name = format!("{}::{}", module_flat.name, r.signature.name.name);
             let run_flat = Identifier::new(runner_name, IDKinds::Function);
-            self.runs.borrow_mut().push(run_flat);
+            self.runs.borrow_mut().push(Run {
+                name: run_flat,
+                signers,
+            });
             module.borrow_mut().functions.push(RefCell::new(r));
         }
 
         Ok(())
     }
 
-    fn generate_record_value_expr(&self, id: &Identifier) -> Expression {
-        let var = match self.env().type_pool.get_type(id).unwrap() {
-            Type::Ref(_) | Type::MutRef(_) => Expression::Variable(VariableAccess {
-                name: id.clone(),
+    /// Add scenarios operating on the resources of a module across transactions.
+    ///
+    /// Each scenario publishes a resource under an account, mutates it, reads
+    /// it and finally removes it, each in a separate `//# run` task, with the
+    /// resource viewed in between so that the persistent storage is compared.
+    fn add_scenarios(&self, u: &mut Unstructured, module: &RefCell<Module>) -> Result<()> {
+        trace!(
+            "Generating scenarios for module: {:?}",
+            module.borrow().name
+        );
+        let structs = module
+            .borrow()
+            .structs
+            .iter()
+            .map(|s| s.borrow().clone())
+            .collect::<Vec<StructDefinition>>();
+        // Any field of the resource can be overwritten and the resource
+        // can be unpacked once it is removed
+        let resources = structs
+            .into_iter()
+            .filter(|s| {
+                s.abilities.contains(&Ability::Key)
+                    && s.type_parameters.type_parameters.is_empty()
+                    && !self.struct_contains_function_value(&s.name)
+                    && s.fields
+                        .iter()
+                        .all(|(_, t)| self.derive_abilities_of_type(t).contains(&Ability::Drop))
+            })
+            .collect::<Vec<StructDefinition>>();
+        if resources.is_empty() {
+            return Ok(());
+        }
+
+        let num_scenarios = self.env().config.num_scenarios_in_module.select(u)?;
+        trace!("NUM: generating {} scenarios", num_scenarios);
+        // Like runners, scenarios only use simple expressions
+        self.env_mut().expr_depth.set_max_depth(0);
+        let module_flat = self.env().id_pool.flatten_access(&module.borrow().name);
+        for idx in 0..num_scenarios {
+            let resource = u.choose(&resources)?.clone();
+            let account = self.choose_signers(u, 1)?.pop().unwrap();
+            let mut steps = Vec::new();
+            for (function, view) in self.generate_scenario(u, &resource, idx)? {
+                let name = format!("{}::{}", module_flat.name, function.signature.name.name);
+                let run = Run {
+                    name: Identifier::new(name, IDKinds::Function),
+                    signers: vec![account.clone()],
+                };
+                steps.push((run, view));
+                module.borrow_mut().functions.push(RefCell::new(function));
+            }
+            self.scenarios.borrow_mut().push(Scenario {
+                account,
+                resource: format!("{}::{}", module_flat.name, resource.name.name),
+                steps,
+            });
+        }
+        self.env_mut().expr_depth.reset_max_depth();
+        Ok(())
+    }
+
+    /// Generate the functions of a scenario on `resource`, each called in a
+    /// transaction of its own, and whether to view the resource after each.
+    ///
+    /// Only publishing checks `exists`, as an earlier runner may have published
+    /// the resource already. The later steps rely on the storage written by
+    /// the previous transactions.
+    fn generate_scenario(
+        &self,
+        u: &mut Unstructured,
+        resource: &StructDefinition,
+        idx: usize,
+    ) -> Result<Vec<(Function, bool)>> {
+        let module_scope = self
+            .env()
+            .id_pool
+            .get_parent_scope_of(&resource.name)
+            .unwrap();
+        let (_, scope) = self.get_next_identifier(IDKinds::Block, &module_scope);
+        let typ = resource.get_type();
+        let sref = self.env().type_pool.get_signer_ref_var();
+        let addr = self.generate_address_of(Expression::Variable(VariableAccess {
+            name: sref.clone(),
+            copy: false,
+        }));
+        let global = |kind: ResourceOperationKind, args: Vec<Expression>| {
+            Expression::Resource(ResourceOperation {
+                kind,
+                typ: typ.clone(),
+                args,
+                index_notation: false,
+            })
+        };
+        let mut steps = Vec::new();
+
+        let value = self.generate_expression_of_type(u, &scope, &typ, false, false)?;
+        let check_exists = global(ResourceOperationKind::Exists, vec![addr.clone()]);
+        let move_to = global(ResourceOperationKind::MoveTo, vec![
+            Expression::Variable(VariableAccess {
+                name: sref.clone(),
                 copy: false,
             }),
-            _ => Expression::Reference(Box::new(Expression::Variable(VariableAccess {
-                name: id.clone(),
+            value,
+        ]);
+        steps.push((
+            "publish",
+            vec![Statement::Expr(Expression::IfElse(Box::new(IfExpr {
+                condition: Expression::UnaryOperation(UnaryOperation::Not(Box::new(check_exists))),
+                body: Block {
+                    name: Identifier::new_str("_exist_check", IDKinds::Block),
+                    stmts: vec![Statement::Expr(move_to)],
+                    return_expr: None,
+                },
+                else_expr: None,
+            })))],
+            true,
+        ));
+
+        // Overwrite some of the fields in place
+        if !resource.fields.is_empty() {
+            let ref_typ = Type::MutRef(Box::new(typ.clone()));
+            let (var, _) = self.get_next_identifier(IDKinds::Var, &scope);
+            self.env_mut().type_pool.insert_mapping(&var, &ref_typ);
+            let mut stmts = vec![Statement::Decl(Declaration {
+                names: vec![var.clone()],
+                typs: vec![ref_typ],
+                value: Some(global(ResourceOperationKind::BorrowGlobalMut, vec![
+                    addr.clone()
+                ])),
+                emit_type: true,
+            })];
+            let first = u.choose_index(resource.fields.len())?;
+            for (i, (field, field_typ)) in resource.fields.iter().enumerate() {
+                if i != first && bool::arbitrary(u)? {
+                    continue;
+                }
+                let lhs = Expression::Dereference(Box::new(Expression::FieldAccess(Box::new(
+                    FieldAccess {
+                        kind: FieldAccessKind::MutBorrow,
+                        base: Expression::Variable(VariableAccess {
+                            name: var.clone(),
+                            copy: false,
+                        }),
+                        field: field.clone(),
+                        position: self.get_field_position(&typ, field),
+                    },
+                ))));
+                let rhs = self.generate_expression_of_type(u, &scope, field_typ, false, false)?;
+                stmts.push(Statement::Expr(Expression::Assign(Box::new(Assignment {
+                    lhs,
+                    op: None,
+                    rhs,
+                }))));
+            }
+            steps.push(("mutate", stmts, true));
+        }
+
+        // Record the resource through an immutable global reference
+        let ref_typ = Type::Ref(Box::new(typ.clone()));
+        let (var, _) = self.get_next_identifier(IDKinds::Var, &scope);
+        self.env_mut().type_pool.insert_mapping(&var, &ref_typ);
+        let mut stmts = vec![Statement::Decl(Declaration {
+            names: vec![var.clone()],
+            typs: vec![ref_typ],
+            value: Some(global(ResourceOperationKind::BorrowGlobal, vec![
+                addr.clone()
+            ])),
+            emit_type: true,
+        })];
+        stmts.extend(self.generate_record_value_expr(&var).map(Statement::Expr));
+        steps.push(("read", stmts, false));
+
+        // Remove the resource and record its value before discarding it
+        let (var, _) = self.get_next_identifier(IDKinds::Var, &scope);
+        self.env_mut().type_pool.insert_mapping(&var, &typ);
+        let mut stmts = vec![Statement::Decl(Declaration {
+            names: vec![var.clone()],
+            typs: vec![typ.clone()],
+            value: Some(global(ResourceOperationKind::MoveFrom, vec![addr])),
+            emit_type: true,
+        })];
+        stmts.extend(self.generate_record_value_expr(&var).map(Statement::Expr));
+        stmts.push(Statement::Unpack(Unpack {
+            pattern: self.generate_discard_pattern(&resource.name),
+            value: Expression::Variable(VariableAccess {
+                name: var,
                 copy: false,
-            }))),
-        };
-        Expression::FunctionCall(FunctionCall {
-            name: Identifier::new_str("record_value", IDKinds::Function),
-            type_args: TypeArgs::default(),
-            args: vec![
-                Expression::Variable(VariableAccess {
-                    name: self.env().type_pool.get_signer_ref_var(),
+            }),
+        }));
+        steps.push(("remove", stmts, true));
+
+        // Like runners, the scenario functions are not stored in the id_pool
+        let sref_dec = Statement::Decl(Declaration {
+            names: vec![sref],
+            typs: vec![Type::Ref(Box::new(Type::Signer))],
+            value: Some(Expression::Reference(Box::new(Expression::Variable(
+                VariableAccess {
+                    name: self.env().type_pool.get_signer_var(),
                     copy: false,
-                }),
-                var,
-            ],
-        })
+                },
+            )))),
+            emit_type: false,
+        });
+        Ok(steps
+            .into_iter()
+            .map(|(step, stmts, view)| {
+                let acquires = match step {
+                    "publish" => BTreeSet::new(),
+                    _ => BTreeSet::from([resource.name.clone()]),
+                };
+                let mut body = vec![sref_dec.clone()];
+                body.extend(stmts);
+                let function = Function {
+                    signature: FunctionSignature {
+                        inline: false,
+                        type_parameters: TypeParameters::default(),
+                        name: Identifier::new(
+                            format!("scenario_{}_{}", idx, step),
+                            IDKinds::Function,
+                        ),
+                        parameters: vec![(self.env().type_pool.get_signer_var(), Type::Signer)],
+                        return_type: None,
+                        fuel: None,
+                        acquires,
+                    },
+                    visibility: Visibility::Public,
+                    is_entry: false,
+                    body: Some(Block {
+                        name: Identifier::new_str("_block_scenario", IDKinds::Block),
+                        stmts: body,
+                        return_expr: None,
+                    }),
+                };
+                (function, view)
+            })
+            .collect())
     }
 
-    /// Generate a runner function for a callee function.
-    /// The runner function does not have parameters so that
-    /// it can be easily called with `//# run`.
-    /// The runner function only contains one function call and have the same return type as the callee.
-    fn generate_runners(
+    /// Generate the spec blocks of a module.
+    ///
+    /// The module spec block declares helper functions and a global invariant
+    /// over the resources of the module. Some structs get data invariants over
+    /// their fields and some functions get pre- and post-conditions.
+    /// Specifications are only type checked, so the conditions need not hold.
+    fn generate_module_specs(
         &self,
         u: &mut Unstructured,
-        callee: &RefCell<Function>,
-    ) -> Result<Vec<Function>> {
-        let signature = callee.borrow().signature.clone();
-        let mut runners = Vec::new();
-        let num_runs = self.env().config.num_runs_per_func.select(u)?;
-        trace!(
-            "NUM: generating {} runners for function: {:?}",
-            num_runs,
-            signature
+        module: &RefCell<Module>,
+        scope: &Scope,
+    ) -> Result<()> {
+        let (_, spec_scope) = self.get_next_identifier(IDKinds::Block, scope);
+        let mut ctx = SpecContext::new(spec_scope);
+        // Only non-generic resources can be read without instantiating them
+        ctx.resources = module
+            .borrow()
+            .structs
+            .iter()
+            .map(|s| s.borrow().clone())
+            .filter(|s| {
+                s.abilities.contains(&Ability::Key) && s.type_parameters.type_parameters.is_empty()
+            })
+            .collect();
+        for c in module.borrow().constants.iter() {
+            if let Some(typ) = self.get_spec_type(&c.typ) {
+                ctx.values
+                    .push((SpecExpression::Variable(c.name.clone()), typ));
+            }
+        }
+
+        let mut specs = Vec::new();
+        let mut members = Vec::new();
+        let num_helpers = self.env().config.num_spec_funs_in_module.select(u)?;
+        trace!("NUM: generating {} spec helper functions", num_helpers);
+        for _ in 0..num_helpers {
+            let helper = self.generate_spec_function(u, &ctx)?;
+            ctx.helpers.push(helper.clone());
+            members.push(SpecMember::Function(helper));
+        }
+
+        let percent = self.env().config.spec_block_percent.min(100);
+        if percent > 0 && !ctx.resources.is_empty() && u.ratio(percent, 100)? {
+            members.push(SpecMember::Condition(
+                self.generate_global_invariant(u, &ctx)?,
+            ));
+        }
+        if !members.is_empty() {
+            specs.push(SpecBlock {
+                target: SpecTarget::Module,
+                members,
+            });
+        }
+
+        for s in module.borrow().structs.iter() {
+            if percent > 0 && u.ratio(percent, 100)? {
+                specs.extend(self.generate_struct_spec(u, &s.borrow(), &ctx)?);
+            }
+        }
+        for f in module.borrow().functions.iter() {
+            if percent > 0 && u.ratio(percent, 100)? {
+                specs.extend(self.generate_function_spec(u, &f.borrow(), &ctx)?);
+            }
+        }
+
+        module.borrow_mut().specs = specs;
+        Ok(())
+    }
+
+    /// Generate a spec helper function over basic values, the constants and
+    /// the resources of the module.
+    fn generate_spec_function(
+        &self,
+        u: &mut Unstructured,
+        ctx: &SpecContext,
+    ) -> Result<SpecFunction> {
+        let (name, fun_scope) = self.get_next_identifier(IDKinds::Function, &ctx.scope);
+        let mut fun_ctx = ctx.clone();
+        fun_ctx.scope = fun_scope;
+
+        let mut params = Vec::new();
+        for _ in 0..u.int_in_range(0..=2)? {
+            let (var, _) = self.get_next_identifier(IDKinds::Var, &fun_ctx.scope);
+            let typ = u.choose(&[Type::Bool, Type::U64, Type::Address])?.clone();
+            fun_ctx
+                .values
+                .push((SpecExpression::Variable(var.clone()), typ.clone()));
+            params.push((var, typ));
+        }
+        let return_type = match bool::arbitrary(u)? {
+            true => Type::Bool,
+            false => Type::U64,
+        };
+        let body = self.generate_spec_expression(u, &fun_ctx, &return_type, 0)?;
+        Ok(SpecFunction {
+            name,
+            params,
+            return_type,
+            body,
+        })
+    }
+
+    /// Generate a global invariant quantifying over all addresses:
+    /// `invariant forall a: address: exists<R>(a) ==> ...;`
+    fn generate_global_invariant(
+        &self,
+        u: &mut Unstructured,
+        ctx: &SpecContext,
+    ) -> Result<SpecCondition> {
+        let (var, _) = self.get_next_identifier(IDKinds::Var, &ctx.scope);
+        let mut body_ctx = ctx.clone();
+        body_ctx
+            .values
+            .push((SpecExpression::Variable(var.clone()), Type::Address));
+        let resource = u.choose(&ctx.resources)?.get_type();
+        let guard =
+            SpecExpression::Exists(resource, Box::new(SpecExpression::Variable(var.clone())));
+        let body = self.generate_spec_expression(u, &body_ctx, &Type::Bool, 1)?;
+        Ok(SpecCondition {
+            kind: SpecConditionKind::Invariant,
+            expr: SpecExpression::Quantifier(Box::new(Quantifier {
+                kind: QuantifierKind::Forall,
+                var,
+                typ: Type::Address,
+                range: None,
+                body: SpecExpression::BinaryOperation(Box::new(SpecBinaryOperation {
+                    op: SpecBinaryOperator::Implies,
+                    lhs: guard,
+                    rhs: body,
+                })),
+            })),
+        })
+    }
+
+    /// Generate a spec block with data invariants over the fields of a struct.
+    /// Data invariants cannot depend on global state, so they only use the
+    /// fields and the constants of the module.
+    fn generate_struct_spec(
+        &self,
+        u: &mut Unstructured,
+        st: &StructDefinition,
+        ctx: &SpecContext,
+    ) -> Result<Option<SpecBlock>> {
+        if st.positional {
+            return Ok(None);
+        }
+        let mut inv_ctx = SpecContext::new(ctx.scope.clone());
+        inv_ctx.values = ctx.values.clone();
+        for (field, typ) in st.fields.iter() {
+            if let Some(typ) = self.get_spec_type(typ) {
+                inv_ctx
+                    .values
+                    .push((SpecExpression::Variable(field.clone()), typ));
+            }
+        }
+        if inv_ctx.values.len() == ctx.values.len() {
+            return Ok(None);
+        }
+
+        let mut members = Vec::new();
+        let num_conds = self.env().config.num_conditions_in_spec.select(u)?;
+        for _ in 0..num_conds {
+            members.push(SpecMember::Condition(SpecCondition {
+                kind: SpecConditionKind::Invariant,
+                expr: self.generate_spec_expression(u, &inv_ctx, &Type::Bool, 0)?,
+            }));
+        }
+        Ok(Some(SpecBlock {
+            target: SpecTarget::Struct(st.name.clone()),
+            members,
+        }))
+    }
+
+    /// Generate a spec block with `requires`, `aborts_if` and `ensures`
+    /// conditions over the parameters of a function.
+    /// Signers are used through their addresses.
+    fn generate_function_spec(
+        &self,
+        u: &mut Unstructured,
+        func: &Function,
+        ctx: &SpecContext,
+    ) -> Result<Option<SpecBlock>> {
+        let sig = &func.signature;
+        if sig.inline || func.body.is_none() {
+            return Ok(None);
+        }
+
+        let mut pre_ctx = ctx.clone();
+        for (name, typ) in sig.parameters.iter() {
+            let var = SpecExpression::Variable(name.clone());
+            let is_signer = match typ {
+                Type::Ref(inner) => inner.as_ref() == &Type::Signer,
+                _ => typ == &Type::Signer,
+            };
+            if is_signer {
+                let addr = SpecExpression::Call(
+                    Identifier::new_str("signer::address_of", IDKinds::Function),
+                    vec![var],
+                );
+                pre_ctx.values.push((addr, Type::Address));
+            } else if let Some(spec_typ) = self.get_spec_type(typ) {
+                if typ.is_mut_ref() {
+                    pre_ctx.old_values.push((var.clone(), spec_typ.clone()));
+                }
+                pre_ctx.values.push((var, spec_typ));
+            }
+        }
+        let mut post_ctx = pre_ctx.clone();
+        post_ctx.allow_old = true;
+        if let Some(typ) = sig.return_type.as_ref().and_then(|t| self.get_spec_type(t)) {
+            post_ctx.values.push((SpecExpression::Result, typ));
+        }
+
+        let mut members = Vec::new();
+        let num_conds = self.env().config.num_conditions_in_spec.select(u)?;
+        for _ in 0..num_conds {
+            let (kind, cond_ctx) = match u.int_in_range(0..=2)? {
+                0 => (SpecConditionKind::Requires, &pre_ctx),
+                1 => (SpecConditionKind::AbortsIf, &pre_ctx),
+                _ => (SpecConditionKind::Ensures, &post_ctx),
+            };
+            members.push(SpecMember::Condition(SpecCondition {
+                kind,
+                expr: self.generate_spec_expression(u, cond_ctx, &Type::Bool, 0)?,
+            }));
+        }
+        Ok(Some(SpecBlock {
+            target: SpecTarget::Function(sig.name.clone()),
+            members,
+        }))
+    }
+
+    /// Get the type used for values of `typ` in specifications.
+    /// References are transparent, and integers are represented by `u64`.
+    /// Returns `None` for types not used by the generated specifications.
+    fn get_spec_type(&self, typ: &Type) -> Option<Type> {
+        match typ {
+            Type::Bool | Type::Address => Some(typ.clone()),
+            Type::Ref(inner) | Type::MutRef(inner) => self.get_spec_type(inner),
+            _ if typ.is_numerical() => Some(Type::U64),
+            _ => None,
+        }
+    }
+
+    /// Get the resources and their fields that can be read as a value of `typ`.
+    fn get_spec_resource_fields(&self, ctx: &SpecContext, typ: &Type) -> Vec<(Type, Identifier)> {
+        let mut fields = Vec::new();
+        for r in ctx.resources.iter().filter(|r| !r.positional) {
+            for (field, field_typ) in r.fields.iter() {
+                if self.get_spec_type(field_typ).as_ref() == Some(typ) {
+                    fields.push((r.get_type(), field.clone()));
+                }
+            }
+        }
+        fields
+    }
+
+    /// Generate a read of the global state of `typ`:
+    /// `global<R>(addr).f` or, for `bool`, also `exists<R>(addr)`.
+    fn generate_spec_global_read(
+        &self,
+        u: &mut Unstructured,
+        ctx: &SpecContext,
+        typ: &Type,
+        depth: usize,
+    ) -> Result<SpecExpression> {
+        let fields = self.get_spec_resource_fields(ctx, typ);
+        let addr = Box::new(self.generate_spec_expression(u, ctx, &Type::Address, depth + 1)?);
+        Ok(
+            match fields.is_empty() || (typ.is_bool() && bool::arbitrary(u)?) {
+                true => SpecExpression::Exists(u.choose(&ctx.resources)?.get_type(), addr),
+                false => {
+                    let (resource, field) = u.choose(&fields)?.clone();
+                    SpecExpression::FieldAccess(
+                        Box::new(SpecExpression::Global(resource, addr)),
+                        field,
+                    )
+                },
+            },
+        )
+    }
+
+    /// Generate a specification expression of `typ`, which is `bool`, `address`
+    /// or `u64` standing for any integer.
+    fn generate_spec_expression(
+        &self,
+        u: &mut Unstructured,
+        ctx: &SpecContext,
+        typ: &Type,
+        depth: usize,
+    ) -> Result<SpecExpression> {
+        let nested = depth < self.env().config.max_spec_expr_depth;
+        let values = ctx
+            .values
+            .iter()
+            .filter(|(_, t)| t == typ)
+            .map(|(e, _)| e.clone())
+            .collect::<Vec<SpecExpression>>();
+        let old_values = ctx
+            .old_values
+            .iter()
+            .filter(|(_, t)| t == typ)
+            .map(|(e, _)| e.clone())
+            .collect::<Vec<SpecExpression>>();
+        let helpers = ctx
+            .helpers
+            .iter()
+            .filter(|f| &f.return_type == typ)
+            .collect::<Vec<&SpecFunction>>();
+        let has_global = !self.get_spec_resource_fields(ctx, typ).is_empty()
+            || (typ.is_bool() && !ctx.resources.is_empty());
+        let is_address = *typ == Type::Address;
+
+        let weights = vec![
+            (0, 2),                                                 // Literal
+            (1, if values.is_empty() { 0 } else { 4 }),             // Value
+            (2, if nested && has_global { 2 } else { 0 }),          // Global read
+            (3, if nested && !helpers.is_empty() { 2 } else { 0 }), // Helper call
+            (
+                4,
+                match nested && ctx.allow_old && (has_global || !old_values.is_empty()) {
+                    true => 2,
+                    false => 0,
+                },
+            ), // Old
+            (5, if nested && !is_address { 4 } else { 0 }),         // Binary operation
+            (6, if nested && typ.is_bool() { 1 } else { 0 }),       // Negation
+            (7, if nested && typ.is_bool() { 2 } else { 0 }),       // Quantifier
+        ];
+        Ok(match choose_item_weighted(u, &weights)? {
+            0 => match typ {
+                Type::Bool => SpecExpression::Boolean(bool::arbitrary(u)?),
+                Type::Address => {
+                    let account = u.choose(&self.env().accounts)?.clone();
+                    SpecExpression::AddressLiteral(format!("@{}", account))
+                },
+                _ => SpecExpression::NumberLiteral(BigUint::from(u.int_in_range(0..=1024u32)?)),
+            },
+            1 => u.choose(&values)?.clone(),
+            2 => self.generate_spec_global_read(u, ctx, typ, depth)?,
+            3 => {
+                let helper = *u.choose(&helpers)?;
+                let mut args = Vec::new();
+                for (_, param_typ) in helper.params.iter() {
+                    args.push(self.generate_spec_expression(u, ctx, param_typ, depth + 1)?);
+                }
+                SpecExpression::Call(helper.name.clone(), args)
+            },
+            4 => {
+                let expr = match old_values.is_empty() || (has_global && bool::arbitrary(u)?) {
+                    true => self.generate_spec_global_read(u, ctx, typ, depth)?,
+                    false => u.choose(&old_values)?.clone(),
+                };
+                SpecExpression::Old(Box::new(expr))
+            },
+            5 => self.generate_spec_binop(u, ctx, typ, depth)?,
+            6 => SpecExpression::Not(Box::new(self.generate_spec_expression(
+                u,
+                ctx,
+                typ,
+                depth + 1,
+            )?)),
+            7 => self.generate_quantifier(u, ctx, depth)?,
+            _ => panic!("Invalid spec expression kind"),
+        })
+    }
+
+    /// Generate a binary operation in a specification producing `typ`.
+    fn generate_spec_binop(
+        &self,
+        u: &mut Unstructured,
+        ctx: &SpecContext,
+        typ: &Type,
+        depth: usize,
+    ) -> Result<SpecExpression> {
+        use SpecBinaryOperator as OP;
+        let (op, operand_typ) = match typ {
+            Type::Bool => match u.int_in_range(0..=2)? {
+                0 => (
+                    u.choose(&[OP::Le, OP::Ge, OP::Leq, OP::Geq])?.clone(),
+                    Type::U64,
+                ),
+                1 => (
+                    u.choose(&[OP::Eq, OP::Neq])?.clone(),
+                    u.choose(&[Type::Bool, Type::U64, Type::Address])?.clone(),
+                ),
+                _ => (
+                    u.choose(&[OP::And, OP::Or, OP::Implies])?.clone(),
+                    Type::Bool,
+                ),
+            },
+            _ => (u.choose(&[OP::Add, OP::Sub, OP::Mul])?.clone(), Type::U64),
+        };
+        let lhs = self.generate_spec_expression(u, ctx, &operand_typ, depth + 1)?;
+        let rhs = self.generate_spec_expression(u, ctx, &operand_typ, depth + 1)?;
+        Ok(SpecExpression::BinaryOperation(Box::new(
+            SpecBinaryOperation { op, lhs, rhs },
+        )))
+    }
+
+    /// Generate a quantified boolean expression over integers, optionally in a
+    /// range, or over addresses if the module has resources.
+    fn generate_quantifier(
+        &self,
+        u: &mut Unstructured,
+        ctx: &SpecContext,
+        depth: usize,
+    ) -> Result<SpecExpression> {
+        let kind = QuantifierKind::arbitrary(u)?;
+        let (var, _) = self.get_next_identifier(IDKinds::Var, &ctx.scope);
+        let typ = match !ctx.resources.is_empty() && bool::arbitrary(u)? {
+            true => Type::Address,
+            false => Type::U64,
+        };
+        let range = match typ == Type::U64 && bool::arbitrary(u)? {
+            true => Some((
+                self.generate_spec_expression(u, ctx, &Type::U64, depth + 1)?,
+                self.generate_spec_expression(u, ctx, &Type::U64, depth + 1)?,
+            )),
+            false => None,
+        };
+        let mut body_ctx = ctx.clone();
+        body_ctx
+            .values
+            .push((SpecExpression::Variable(var.clone()), typ.clone()));
+        let body = self.generate_spec_expression(u, &body_ctx, &Type::Bool, depth + 1)?;
+        Ok(SpecExpression::Quantifier(Box::new(Quantifier {
+            kind,
+            var,
+            typ,
+            range,
+            body,
+        })))
+    }
+
+    /// Generate a call recording the value of the variable `id`.
+    /// Returns `None` if the value contains a function value, which may not be
+    /// serializable.
+    fn generate_record_value_expr(&self, id: &Identifier) -> Option<Expression> {
+        let typ = self.env().type_pool.get_type(id).unwrap();
+        if self.contains_function_value(&typ) {
+            return None;
+        }
+        let var = match typ {
+            Type::Ref(_) | Type::MutRef(_) => Expression::Variable(VariableAccess {
+                name: id.clone(),
+                copy: false,
+            }),
+            _ => Expression::Reference(Box::new(Expression::Variable(VariableAccess {
+                name: id.clone(),
+                copy: false,
+            }))),
+        };
+        Some(Expression::FunctionCall(FunctionCall {
+            name: Identifier::new_str("record_value", IDKinds::Function),
+            type_args: TypeArgs::default(),
+            args: vec![
+                Expression::Variable(VariableAccess {
+                    name: self.env().type_pool.get_signer_ref_var(),
+                    copy: false,
+                }),
+                var,
+            ],
+            receiver: false,
+        }))
+    }
+
+    /// Generate a runner function for a callee function.
+    /// The runner function does not have parameters so that
+    /// it can be easily called with `//# run`.
+    /// The runner function only contains one function call and have the same return type as the callee.
+    /// Each runner is returned with the accounts signing its transaction.
+    fn generate_runners(
+        &self,
+        u: &mut Unstructured,
+        callee: &RefCell<Function>,
+    ) -> Result<Vec<(Function, Vec<String>)>> {
+        let signature = callee.borrow().signature.clone();
+        let module_scope = self
+            .env()
+            .id_pool
+            .get_parent_scope_of(&signature.name)
+            .unwrap();
+        let mut runners = Vec::new();
+        let num_runs = self.env().config.num_runs_per_func.select(u)?;
+        trace!(
+            "NUM: generating {} runners for function: {:?}",
+            num_runs,
+            signature
         );
         for i in 0..num_runs {
             let sref_dec = Statement::Decl(Declaration {
@@ -443,20 +1577,24 @@ impl MoveSmith {
                 emit_type: false,
             });
 
-            // Generate a call to the target function
-            let call = Expression::FunctionCall(self.generate_call_to_function(
-                u,
-                &ROOT_SCOPE,
-                &signature,
-                None,
-                false,
-            )?);
+            let (call, parameters) = self.generate_call_from_root(u, &signature, &module_scope)?;
+            let signers = self.choose_signers(u, parameters.len())?;
+            let call = Expression::FunctionCall(call);
 
             // If the callee returns a type parameter, we ignore the return.
-            let new_ret = match &signature.return_type {
-                Some(Type::TypeParameter(_)) => None,
-                Some(t) => Some(t.clone()),
-                None => None,
+            // A returned reference may borrow from the arguments of the call,
+            // so the runner returns the value behind it instead.
+            let (new_ret, call) = match &signature.return_type {
+                Some(Type::TypeParameter(_)) => (None, call),
+                Some(Type::Ref(inner)) | Some(Type::MutRef(inner)) if inner.is_type_parameter() => {
+                    (None, call)
+                },
+                Some(Type::Ref(inner)) | Some(Type::MutRef(inner)) => (
+                    Some(inner.as_ref().clone()),
+                    Expression::Dereference(Box::new(call)),
+                ),
+                Some(t) => (Some(t.clone()), call),
+                None => (None, call),
             };
 
             // Generate a body with only one statement/return expr
@@ -484,149 +1622,630 @@ impl MoveSmith {
                         format!("{}_runner_{}", signature.name.name, i),
                         IDKinds::Function,
                     ),
-                    parameters: vec![(self.env().type_pool.get_signer_var(), Type::Signer)],
+                    parameters,
                     return_type: new_ret,
+                    fuel: None,
                     acquires: signature.acquires.clone(),
                 },
-                visibility: Visibility { public: true },
+                visibility: Visibility::Public,
+                is_entry: false,
                 body: Some(body),
             };
-            runners.push(runner);
+            runners.push((runner, signers));
         }
         Ok(runners)
     }
 
-    // Generate a struct skeleton with name and random abilities.
-    fn generate_struct_skeleton(
+    /// Generate a call to `signature` from a function in the root scope taking
+    /// only signers, e.g. a runner or a unit test.
+    /// Returns the call and the signer parameters the caller must take.
+    fn generate_call_from_root(
         &self,
         u: &mut Unstructured,
-        parent_scope: &Scope,
-    ) -> Result<StructDefinition> {
-        let (name, struct_scope) = self.get_next_identifier(IDKinds::Struct, parent_scope);
-
-        // Generate type parameters for the struct
-        // NOTE: All parameters will have copy+drop for now to avoid having no expression to generate
-        let mut type_parameters = Vec::new();
-        let num_tps = self.env().config.num_type_params_in_struct.select(u)?;
-        trace!(
-            "NUM: generating {} type parameters for struct: {:?}",
-            num_tps,
-            name
-        );
-        for _ in 0..num_tps {
-            type_parameters.push(self.generate_type_parameter(
-                u,
-                &struct_scope,
-                false,
-                Some(vec![Ability::Copy, Ability::Drop]),
-                None,
-            )?);
+        signature: &FunctionSignature,
+        module_scope: &Scope,
+    ) -> Result<(FunctionCall, Vec<(Identifier, Type)>)> {
+        // Generate a call to the target function.
+        // The root scope has no `key` types, so `key` type parameters
+        // are given a struct of the module here.
+        let mut desired_types = Vec::new();
+        for tp in signature.type_parameters.type_parameters.iter() {
+            let desired = match tp.abilities.contains(&Ability::Key) {
+                true => {
+                    let mut requires = vec![Ability::Copy, Ability::Drop];
+                    requires.extend(tp.abilities.iter().cloned());
+                    let mut choices = self.get_types_with_abilities(module_scope, &requires, false);
+                    choices.retain(|t| match t {
+                        Type::Struct(st) => st.type_parameters.type_parameters.is_empty(),
+                        _ => false,
+                    });
+                    Some(u.choose(&choices)?.clone())
+                },
+                false => None,
+            };
+            desired_types.push(desired);
         }
-        let type_parameters = TypeParameters { type_parameters };
+        let mut call = self.generate_call_with_type_args(
+            u,
+            &ROOT_SCOPE,
+            signature,
+            desired_types,
+            false,
+            None,
+        )?;
 
-        // Generate the `has` abilities for the struct
-        let mut ability_choices = vec![Ability::Store, Ability::Key];
-        // NOTE: again copy+drop by default for now
-        let mut abilities = vec![Ability::Drop, Ability::Copy];
-        for _ in 0..u.int_in_range(0..=0)? {
-            let idx = u.int_in_range(0..=(ability_choices.len() - 1))?;
-            abilities.push(ability_choices.remove(idx));
+        // Each additional signer parameter of the callee is given
+        // a signer of its own from the caller
+        let signer_var = self.env().type_pool.get_signer_var();
+        let mut parameters = vec![(signer_var.clone(), Type::Signer)];
+        let signer_ref = Type::Ref(Box::new(Type::Signer));
+        let sref_idx = signature
+            .parameters
+            .iter()
+            .position(|(_, t)| t == &signer_ref);
+        for (idx, (_, typ)) in signature.parameters.iter().enumerate() {
+            if typ == &signer_ref && Some(idx) != sref_idx {
+                let name = Identifier::new(
+                    format!("{}{}", signer_var.name, parameters.len()),
+                    IDKinds::Var,
+                );
+                call.args[idx] =
+                    Expression::Reference(Box::new(Expression::Variable(VariableAccess {
+                        name: name.clone(),
+                        copy: false,
+                    })));
+                parameters.push((name, Type::Signer));
+            }
         }
-
-        // Register the struct type and name
-        let struct_typ = Type::new_struct(&name, Some(&type_parameters));
-        self.env_mut().type_pool.insert_mapping(&name, &struct_typ);
-        self.env_mut().type_pool.register_type(struct_typ);
-        Ok(StructDefinition {
-            name,
-            abilities,
-            type_parameters,
-            fields: Vec::new(),
-        })
+        Ok((call, parameters))
     }
 
-    /// Fill in the struct fields with random types.
-    fn fill_struct(
-        &self,
-        u: &mut Unstructured,
-        st: &RefCell<StructDefinition>,
-        parent_scope: &Scope,
-    ) -> Result<()> {
-        let struct_scope = self.env().id_pool.get_scope_for_children(&st.borrow().name);
-        let num_fields = self.env().config.num_fields_in_struct.select(u)?;
+    /// Add `#[test]` functions calling the functions of a module.
+    fn add_unit_tests(&self, u: &mut Unstructured, module: &RefCell<Module>) -> Result<()> {
         trace!(
-            "NUM: generating {} fields for struct: {:?}",
-            num_fields,
-            st.borrow().name
+            "Generating unit tests for module: {:?}",
+            module.borrow().name
         );
-        for _ in 0..num_fields {
-            let (name, _) = self.get_next_identifier(IDKinds::Var, &struct_scope);
+        // Like runners, unit tests only use simple expressions
+        self.env_mut().expr_depth.set_max_depth(0);
+        let mut tests = Vec::new();
+        for f in module.borrow().functions.iter() {
+            let num_tests = self.env().config.num_unit_tests_per_func.select(u)?;
+            for i in 0..num_tests {
+                tests.extend(self.generate_unit_test(u, f, i)?);
+            }
+        }
+        self.env_mut().expr_depth.reset_max_depth();
+        module.borrow_mut().tests = tests;
+        Ok(())
+    }
 
-            let typ = loop {
-                match u.int_in_range(0..=2)? {
-                    // More chance to use basic types than struct types
-                    0 | 1 => {
-                        break self.get_random_type(
-                            u,
-                            &struct_scope,
-                            true,
-                            false,
-                            true,
-                            false,
-                            false,
-                        )?
-                    },
-                    // Use another struct as the field
-                    2 => {
-                        // We can no longer generate struct types if we reach the limit
-                        if self.env_mut().reached_struct_type_field_limit(u) {
-                            break self.get_random_type(
+    /// Generate a unit test calling `callee`.
+    ///
+    /// If the callee returns a primitive value, it is called twice with the
+    /// same arguments and the test asserts that both results are equal.
+    /// Returns `None` if the result of the callee cannot be dropped.
+    ///
+    /// An `#[expected_failure]` test aborts at a known site after the calls:
+    /// either the assert comparing the two results is negated, or a stdlib
+    /// function is called with out-of-range arguments.
+    fn generate_unit_test(
+        &self,
+        u: &mut Unstructured,
+        callee: &RefCell<Function>,
+        idx: usize,
+    ) -> Result<Option<UnitTest>> {
+        let signature = callee.borrow().signature.clone();
+        if signature.inline {
+            return Ok(None);
+        }
+        let module_scope = self
+            .env()
+            .id_pool
+            .get_parent_scope_of(&signature.name)
+            .unwrap();
+        // Results of type parameters are instantiated with droppable types
+        let ret = match &signature.return_type {
+            Some(Type::TypeParameter(_)) | None => None,
+            Some(Type::Ref(inner)) | Some(Type::MutRef(inner)) if inner.is_type_parameter() => None,
+            Some(typ) => {
+                let abilities = self.derive_abilities_of_type(typ);
+                if !typ.is_num_or_bool() && !abilities.contains(&Ability::Drop) {
+                    return Ok(None);
+                }
+                Some(typ.clone())
+            },
+        };
+
+        let (call, parameters) = self.generate_call_from_root(u, &signature, &module_scope)?;
+        let signers = self.choose_signers(u, parameters.len())?;
+        let call = Expression::FunctionCall(call);
+
+        let sref = self.env().type_pool.get_signer_ref_var();
+        let mut stmts = vec![
+            Statement::Decl(Declaration {
+                names: vec![sref.clone()],
+                typs: vec![Type::Ref(Box::new(Type::Signer))],
+                value: Some(Expression::Reference(Box::new(Expression::Variable(
+                    VariableAccess {
+                        name: self.env().type_pool.get_signer_var(),
+                        copy: false,
+                    },
+                )))),
+                emit_type: false,
+            }),
+            Statement::Expr(Expression::FunctionCall(FunctionCall {
+                name: Identifier::new_str(
+                    "0xCAFE::FuzzStore::init_accumulated_hash_for_test",
+                    IDKinds::Function,
+                ),
+                type_args: TypeArgs::default(),
+                args: vec![Expression::Variable(VariableAccess {
+                    name: sref,
+                    copy: false,
+                })],
+                receiver: false,
+            })),
+        ];
+        let percent = self.env().config.expected_failure_percent.min(100);
+        let expect_failure = percent > 0 && u.ratio(percent, 100)?;
+        let mut expected_failure = None;
+        match ret {
+            Some(typ) if typ.is_num_or_bool() || typ == Type::Address => {
+                let (_, test_scope) = self.get_next_identifier(IDKinds::Block, &ROOT_SCOPE);
+                let mut results = Vec::new();
+                for _ in 0..2 {
+                    let (name, _) = self.get_next_identifier(IDKinds::Var, &test_scope);
+                    stmts.push(Statement::Decl(Declaration {
+                        typs: vec![typ.clone()],
+                        names: vec![name.clone()],
+                        value: Some(call.clone()),
+                        emit_type: true,
+                    }));
+                    results.push(Expression::Variable(VariableAccess { name, copy: false }));
+                }
+                let rhs = results.pop().unwrap();
+                let lhs = results.pop().unwrap();
+                let (code, value) = self.generate_error_code(&module_scope);
+                let op = match expect_failure && bool::arbitrary(u)? {
+                    true => {
+                        expected_failure = Some(ExpectedFailure {
+                            abort_code: value,
+                            location: module_scope.0.clone().unwrap(),
+                        });
+                        EqualityBinaryOperator::Neq
+                    },
+                    false => EqualityBinaryOperator::Eq,
+                };
+                stmts.push(Statement::Expr(Expression::Assert(Box::new(Assert {
+                    condition: Expression::BinaryOperation(Box::new(BinaryOperation {
+                        op: BinaryOperator::Equality(op),
+                        lhs,
+                        rhs,
+                    })),
+                    code,
+                }))));
+            },
+            Some(typ) => stmts.push(Statement::Decl(Declaration {
+                typs: vec![typ],
+                names: vec![Identifier::new_str("_", IDKinds::Var)],
+                value: Some(call),
+                emit_type: true,
+            })),
+            None => stmts.push(Statement::Expr(call)),
+        }
+        if expect_failure && expected_failure.is_none() {
+            let aborting = u.choose(ABORTING_CALLS)?;
+            let args = aborting
+                .args
+                .iter()
+                .map(|arg| {
+                    Expression::NumberLiteral(NumberLiteral {
+                        value: BigUint::from(*arg),
+                        typ: Type::U64,
+                    })
+                })
+                .collect();
+            stmts.push(Statement::Expr(Expression::FunctionCall(FunctionCall {
+                name: Identifier::new(aborting.full_name(), IDKinds::Function),
+                type_args: TypeArgs::default(),
+                args,
+                receiver: false,
+            })));
+            expected_failure = Some(ExpectedFailure {
+                abort_code: aborting.abort_code,
+                location: aborting.location(),
+            });
+        }
+
+        // Like runners, unit tests are not stored in the id_pool
+        let function = Function {
+            signature: FunctionSignature {
+                inline: false,
+                type_parameters: TypeParameters::default(),
+                name: Identifier::new(
+                    format!("{}_test_{}", signature.name.name, idx),
+                    IDKinds::Function,
+                ),
+                parameters: parameters.clone(),
+                return_type: None,
+                fuel: None,
+                acquires: signature.acquires.clone(),
+            },
+            visibility: Visibility::Private,
+            is_entry: false,
+            body: Some(Block {
+                name: Identifier::new_str("_block_test", IDKinds::Block),
+                stmts,
+                return_expr: None,
+            }),
+        };
+        Ok(Some(UnitTest {
+            signers: parameters
+                .into_iter()
+                .map(|(name, _)| name)
+                .zip(signers)
+                .collect(),
+            expected_failure,
+            function,
+        }))
+    }
+
+    /// Choose `num` distinct accounts in random order to sign a transaction.
+    fn choose_signers(&self, u: &mut Unstructured, num: usize) -> Result<Vec<String>> {
+        let mut accounts = self.env().accounts.clone();
+        let mut signers = Vec::new();
+        for _ in 0..num {
+            let idx = u.choose_index(accounts.len())?;
+            signers.push(accounts.remove(idx));
+        }
+        Ok(signers)
+    }
+
+    // Generate a struct skeleton with name and random abilities.
+    // A `linear` struct has neither copy nor drop. It may have a type parameter
+    // without copy or drop, which is the type of its first field.
+    fn generate_struct_skeleton(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        linear: bool,
+    ) -> Result<StructDefinition> {
+        let (name, struct_scope) = self.get_next_identifier(IDKinds::Struct, parent_scope);
+
+        // Generate the `has` abilities for the struct
+        let mut ability_choices = vec![Ability::Store, Ability::Key];
+        // NOTE: again copy+drop by default for now
+        let mut abilities = vec![Ability::Drop, Ability::Copy];
+        for _ in 0..u.int_in_range(0..=0)? {
+            let idx = u.int_in_range(0..=(ability_choices.len() - 1))?;
+            abilities.push(ability_choices.remove(idx));
+        }
+        if linear {
+            abilities.clear();
+            for ability in ability_choices {
+                if bool::arbitrary(u)? {
+                    abilities.push(ability);
+                }
+            }
+        }
+
+        // Generate type parameters for the struct
+        // NOTE: Parameters of the other structs have copy+drop for now to avoid
+        // having no expression to generate
+        let mut type_parameters = Vec::new();
+        let num_tps = match linear {
+            true => u.int_in_range(0..=1)?,
+            false => self.env().config.num_type_params_in_struct.select(u)?,
+        };
+        trace!(
+            "NUM: generating {} type parameters for struct: {:?}",
+            num_tps,
+            name
+        );
+        for _ in 0..num_tps {
+            let tp = match linear {
+                // The field of a resource or a stored struct must have store
+                true => {
+                    let include = match abilities.is_empty() {
+                        true => None,
+                        false => Some(vec![Ability::Store]),
+                    };
+                    self.generate_type_parameter(
+                        u,
+                        &struct_scope,
+                        false,
+                        include,
+                        Some(vec![Ability::Copy, Ability::Drop, Ability::Key]),
+                    )?
+                },
+                false => self.generate_type_parameter(
+                    u,
+                    &struct_scope,
+                    false,
+                    Some(vec![Ability::Copy, Ability::Drop]),
+                    None,
+                )?,
+            };
+            type_parameters.push(tp);
+        }
+        let type_parameters = TypeParameters { type_parameters };
+
+        // Register the struct type and name
+        let struct_typ = Type::new_struct(&name, Some(&type_parameters));
+        self.env_mut().type_pool.insert_mapping(&name, &struct_typ);
+        self.env_mut().type_pool.register_type(struct_typ);
+        Ok(StructDefinition {
+            name,
+            abilities,
+            type_parameters,
+            positional: false,
+            fields: Vec::new(),
+        })
+    }
+
+    /// Generate a module constant with a literal value.
+    /// The constant is accessible anywhere in the module.
+    fn generate_constant(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<Constant> {
+        let (name, _) = self.get_next_identifier(IDKinds::Constant, parent_scope);
+        let typ = self.get_random_constant_type(u, 0)?;
+        let value = self.generate_constant_value(u, &typ)?;
+        self.env_mut().type_pool.insert_mapping(&name, &typ);
+        Ok(Constant { typ, name, value })
+    }
+
+    /// Returns a random type that is allowed for constants, i.e. a primitive
+    /// type or a (nested) vector of primitive types.
+    fn get_random_constant_type(&self, u: &mut Unstructured, depth: usize) -> Result<Type> {
+        // Limit the nesting of vectors
+        let vector_weight = match depth {
+            0 => 4,
+            1 => 2,
+            _ => 0,
+        };
+        // `None` stands for a vector of another random constant type
+        let choices = [
+            (Some(Type::U8), 4),
+            (Some(Type::U16), 2),
+            (Some(Type::U32), 2),
+            (Some(Type::U64), 3),
+            (Some(Type::U128), 2),
+            (Some(Type::U256), 2),
+            (Some(Type::Bool), 3),
+            (Some(Type::Address), 2),
+            (Some(Type::Vector(Box::new(Type::U8))), 4),
+            (None, vector_weight),
+        ];
+        let choices = choices
+            .into_iter()
+            .filter(|(_, w)| *w > 0)
+            .collect::<Vec<(Option<Type>, u32)>>();
+        Ok(match choose_item_weighted(u, &choices)? {
+            Some(typ) => typ,
+            None => Type::Vector(Box::new(self.get_random_constant_type(u, depth + 1)?)),
+        })
+    }
+
+    /// Generate a literal value of a constant type.
+    /// Boundary values are preferred to exercise constant folding.
+    fn generate_constant_value(&self, u: &mut Unstructured, typ: &Type) -> Result<Expression> {
+        Ok(match typ {
+            Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::U256 => {
+                let max = (BigUint::from(1u32) << typ.num_bits().unwrap()) - BigUint::from(1u32);
+                let value = match u.int_in_range(0..=5)? {
+                    0 => BigUint::from(0u32),
+                    1 => BigUint::from(1u32),
+                    2 => max,
+                    3 => max - BigUint::from(1u32),
+                    _ => {
+                        self.generate_number_literal(u, Some(typ), None, None)?
+                            .value
+                    },
+                };
+                Expression::NumberLiteral(NumberLiteral {
+                    value,
+                    typ: typ.clone(),
+                })
+            },
+            Type::Bool => Expression::Boolean(bool::arbitrary(u)?),
+            Type::Address => {
+                let addr = match u.int_in_range(0..=3)? {
+                    0 => "0x0".to_string(),
+                    1 => format!("0x{}", "f".repeat(64)),
+                    _ => u.choose(&self.env().accounts)?.clone(),
+                };
+                Expression::AddressLiteral(format!("@{}", addr))
+            },
+            Type::Vector(inner) => {
+                let literal = match u.int_in_range(0..=3)? {
+                    0 => VectorLiteral::Empty(inner.as_ref().clone()),
+                    1 if inner.as_ref() == &Type::U8 => {
+                        let mut hex = String::new();
+                        let num_bytes = self.env().config.hex_byte_str_size.select(u)?;
+                        for _ in 0..num_bytes {
+                            hex.push_str(&format!("{:02x}", u8::arbitrary(u)?));
+                        }
+                        VectorLiteral::HexString(hex)
+                    },
+                    _ => {
+                        let mut elems = vec![];
+                        for _ in 0..u.int_in_range(1..=3)? {
+                            elems.push(self.generate_constant_value(u, inner)?);
+                        }
+                        VectorLiteral::Multiple(inner.as_ref().clone(), elems)
+                    },
+                };
+                Expression::VectorLiteral(literal)
+            },
+            _ => panic!("Invalid constant type: {:?}", typ),
+        })
+    }
+
+    /// Generate an enum definition with struct-like, positional and
+    /// field-less variants.
+    fn generate_enum(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<EnumDefinition> {
+        let (name, enum_scope) = self.get_next_identifier(IDKinds::Enum, parent_scope);
+
+        // NOTE: copy+drop by default, same as structs
+        let mut abilities = vec![Ability::Drop, Ability::Copy];
+        if bool::arbitrary(u)? {
+            abilities.push(Ability::Store);
+        }
+
+        let num_variants = self.env().config.num_variants_in_enum.select(u)?.max(1);
+        trace!(
+            "NUM: generating {} variants for enum: {:?}",
+            num_variants,
+            name
+        );
+        let mut variants = Vec::new();
+        for _ in 0..num_variants {
+            let (variant_name, variant_scope) =
+                self.get_next_identifier(IDKinds::Variant, &enum_scope);
+            let positional = bool::arbitrary(u)?;
+
+            let num_fields = self.env().config.num_fields_in_variant.select(u)?;
+            let mut fields = Vec::new();
+            for _ in 0..num_fields {
+                let (field, _) = self.get_next_identifier(IDKinds::Var, &variant_scope);
+                let typ =
+                    self.get_random_type(u, &variant_scope, true, false, false, false, false)?;
+                self.env_mut().type_pool.insert_mapping(&field, &typ);
+                fields.push((field, typ));
+            }
+            variants.push(Variant {
+                name: variant_name,
+                positional,
+                fields,
+            });
+        }
+
+        // Register the enum type and name
+        let enum_typ = Type::Enum(name.clone());
+        self.env_mut().type_pool.insert_mapping(&name, &enum_typ);
+        self.env_mut().type_pool.register_type(enum_typ);
+        Ok(EnumDefinition {
+            name,
+            abilities,
+            variants,
+        })
+    }
+
+    /// Fill in the struct fields with random types.
+    fn fill_struct(
+        &self,
+        u: &mut Unstructured,
+        st: &RefCell<StructDefinition>,
+        parent_scope: &Scope,
+    ) -> Result<()> {
+        let struct_scope = self.env().id_pool.get_scope_for_children(&st.borrow().name);
+        let num_fields = self.env().config.num_fields_in_struct.select(u)?;
+        trace!(
+            "NUM: generating {} fields for struct: {:?}",
+            num_fields,
+            st.borrow().name
+        );
+        // Positional structs are only supported by Move 2
+        st.borrow_mut().positional = num_fields > 0 && self.allow_move2() && bool::arbitrary(u)?;
+        // Function values do not have store, so only the other structs can hold them.
+        // Function values are only supported by Move 2.
+        let percent = match !self.allow_move2()
+            || st
+                .borrow()
+                .abilities
+                .iter()
+                .any(|a| matches!(a, Ability::Store | Ability::Key))
+        {
+            true => 0,
+            false => self.env().config.function_value_field_percent.min(100),
+        };
+        // The type parameter of a linear struct is only used by its first field,
+        // which then holds a linear value if the type argument is linear
+        if st.borrow().abilities.iter().all(|a| *a != Ability::Copy) {
+            let tps = st.borrow().type_parameters.type_parameters.clone();
+            for tp in tps {
+                let (name, _) = self.get_next_identifier(IDKinds::Var, &struct_scope);
+                let typ = Type::TypeParameter(tp);
+                self.env_mut().type_pool.insert_mapping(&name, &typ);
+                st.borrow_mut().fields.push((name, typ));
+            }
+        }
+        for _ in 0..num_fields {
+            let (name, _) = self.get_next_identifier(IDKinds::Var, &struct_scope);
+
+            let typ = if percent > 0 && u.ratio(percent, 100)? {
+                Type::Function(self.generate_function_type(u, &struct_scope)?)
+            } else {
+                loop {
+                    match u.int_in_range(0..=2)? {
+                        // More chance to use basic types than struct types
+                        0 | 1 => {
+                            break self.get_random_type(
                                 u,
                                 &struct_scope,
                                 true,
                                 false,
+                                true,
                                 false,
                                 false,
-                                false,
-                            )?;
-                        }
-
-                        // Get all structs in scope and satisfy the ability requirements
-                        let candidates = self.get_usable_struct_type(
-                            st.borrow().abilities.clone(),
-                            parent_scope,
-                            &st.borrow().name,
-                        );
-                        if !candidates.is_empty() {
-                            let struc_def = u.choose(&candidates)?;
-
-                            let constraints = st.borrow().abilities.clone();
-                            let mut new_typ = struc_def.get_type();
-
-                            // Check if a struct needs type parameters
-                            if self.is_type_concretizable(&new_typ, &struct_scope) {
-                                new_typ = self
-                                    .concretize_type(
-                                        u,
-                                        &new_typ,
-                                        &struct_scope,
-                                        constraints,
-                                        Some(&st.borrow().get_type()),
-                                    )
-                                    .unwrap();
+                            )?
+                        },
+                        // Use another struct as the field
+                        2 => {
+                            // We can no longer generate struct types if we reach the limit
+                            if self.env_mut().reached_struct_type_field_limit(u) {
+                                break self.get_random_type(
+                                    u,
+                                    &struct_scope,
+                                    true,
+                                    false,
+                                    false,
+                                    false,
+                                    false,
+                                )?;
                             }
 
-                            // We can only use fully concretized type as a field
-                            if let Type::StructConcrete(_) = &new_typ {
-                                // Check if we create a cyclic data type
-                                if !self.check_struct_reachable(&new_typ, &st.borrow().name, None) {
-                                    self.env_mut().inc_struct_type_field_counter();
-                                    break new_typ;
+                            // Get all structs in scope and satisfy the ability requirements
+                            let candidates = self.get_usable_struct_type(
+                                st.borrow().abilities.clone(),
+                                parent_scope,
+                                &st.borrow().name,
+                            );
+                            if !candidates.is_empty() {
+                                let struc_def = u.choose(&candidates)?;
+
+                                let constraints = st.borrow().abilities.clone();
+                                let mut new_typ = struc_def.get_type();
+
+                                // Check if a struct needs type parameters
+                                if self.is_type_concretizable(&new_typ, &struct_scope) {
+                                    new_typ = self
+                                        .concretize_type(
+                                            u,
+                                            &new_typ,
+                                            &struct_scope,
+                                            constraints,
+                                            Some(&st.borrow().get_type()),
+                                        )
+                                        .unwrap();
+                                }
+
+                                // We can only use fully concretized type as a field
+                                if let Type::StructConcrete(_) = &new_typ {
+                                    // Check if we create a cyclic data type
+                                    if !self.check_struct_reachable(
+                                        &new_typ,
+                                        &st.borrow().name,
+                                        None,
+                                    ) {
+                                        self.env_mut().inc_struct_type_field_counter();
+                                        break new_typ;
+                                    }
                                 }
                             }
-                        }
-                    },
-                    _ => panic!("Invalid type"),
+                        },
+                        _ => panic!("Invalid type"),
+                    }
                 }
             };
             // Keeps track of the type of the field
@@ -656,6 +2275,10 @@ impl MoveSmith {
                 if !desired.iter().all(|a| struct_def.abilities.contains(a)) {
                     return None;
                 }
+                // Linear structs are never used as fields
+                if self.is_linear(&struct_def.get_type()) {
+                    return None;
+                }
                 if desired.contains(&Ability::Key)
                     && !struct_def.abilities.contains(&Ability::Store)
                 {
@@ -751,18 +2374,57 @@ impl MoveSmith {
         None
     }
 
+    /// Get the enum definition with the given identifier.
+    fn get_enum_definition_with_identifier(&self, id: &Identifier) -> Option<EnumDefinition> {
+        for m in self.modules.iter() {
+            for e in m.borrow().enums.iter() {
+                if &e.borrow().name == id {
+                    return Some(e.borrow().clone());
+                }
+            }
+        }
+        None
+    }
+
     /// Generate a function skeleton with name and signature.
     fn generate_function_skeleton(
         &self,
         u: &mut Unstructured,
         parent_scope: &Scope,
+        linear_structs: &[Type],
+        ref_structs: &[Type],
     ) -> Result<Function> {
         let (name, scope) = self.get_next_identifier(IDKinds::Function, parent_scope);
-        let signature: FunctionSignature = self.generate_function_signature(u, &scope, name)?;
+
+        // Private functions can only be called within the module,
+        // the others can also be called from the modules depending on it
+        let visibility = choose_item_weighted(u, &[
+            (Visibility::Public, 4),
+            (Visibility::Friend, 2),
+            // `public(package)` is only supported by Move 2
+            (Visibility::Package, if self.allow_move2() { 2 } else { 0 }),
+            (Visibility::Private, 1),
+        ])?;
+
+        // Public entry functions can also be called from scripts
+        let percent = self.env().config.entry_func_percent.min(100);
+        let is_entry = matches!(visibility, Visibility::Public | Visibility::Private)
+            && percent > 0
+            && u.ratio(percent, 100)?;
+
+        let signature: FunctionSignature = self.generate_function_signature(
+            u,
+            &scope,
+            name,
+            linear_structs,
+            ref_structs,
+            is_entry,
+        )?;
 
         let func = Function {
             signature,
-            visibility: Visibility { public: true },
+            visibility,
+            is_entry,
             body: None,
         };
         trace!("Generated function signature: {:?}", func.inline());
@@ -800,29 +2462,43 @@ impl MoveSmith {
     /// We need to make sure that if the return type is a type parameter,
     /// at least one of the parameters have this type.
     /// Otherwise, we cannot instantiate this type for return.
+    ///
+    /// `linear_structs` are the non-generic structs without copy and drop in
+    /// the module, which can be used as the type of a linear parameter.
+    /// `ref_structs` are the non-generic structs with copy in the module,
+    /// which can be borrowed by a function returning a reference.
+    /// A Move 2 function may take a reference to one of `ref_structs` as its
+    /// first parameter `self`.
+    /// An `entry` function is not generic, returns nothing and only takes
+    /// signers and values of basic types, which a transaction can provide.
     fn generate_function_signature(
         &self,
         u: &mut Unstructured,
         parent_scope: &Scope,
         name: Identifier,
+        linear_structs: &[Type],
+        ref_structs: &[Type],
+        entry: bool,
     ) -> Result<FunctionSignature> {
         // First generate type parameters so that they can be used in the parameters and return type
         let mut type_parameters = Vec::new();
-        let num_tps = self.env().config.num_type_params_in_func.select(u)?;
+        let num_tps = match entry {
+            true => 0,
+            false => self.env().config.num_type_params_in_func.select(u)?,
+        };
         trace!(
             "NUM: generating {} type parameters for function: {:?}",
             num_tps,
             name
         );
+        // Type parameters without copy or drop are used by the linear parameters below
         for _ in 0..num_tps {
             type_parameters.push(self.generate_type_parameter(
                 u,
                 parent_scope,
                 false,
-                // TODO: again, default copy+drop for now
-                Some(vec![Ability::Copy, Ability::Drop]),
-                // TODO: remove this when implementing global storage
-                Some(vec![Ability::Key]),
+                None,
+                None,
             )?);
         }
 
@@ -832,6 +2508,34 @@ impl MoveSmith {
             Type::Ref(Box::new(Type::Signer)),
         )];
 
+        // Additional signers give access to the global storage of other accounts.
+        // Each of them must be a different account when the function is run.
+        let num_signers = self
+            .env()
+            .config
+            .num_signer_params_in_func
+            .select(u)?
+            .min(self.env().accounts.len() - 1);
+        for _ in 0..num_signers {
+            let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+            let typ = Type::Ref(Box::new(Type::Signer));
+            self.env_mut().type_pool.insert_mapping(&name, &typ);
+            parameters.push((name, typ));
+        }
+
+        // A function with fuel can be called recursively, so it cannot be inline
+        let percent = self.env().config.fuel_func_percent.min(100);
+        let fuel = match percent > 0 && u.ratio(percent, 100)? {
+            true => {
+                let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+                self.env_mut().type_pool.insert_mapping(&name, &Type::U64);
+                self.env_mut().mark_read_only(&name);
+                parameters.push((name.clone(), Type::U64));
+                Some(name)
+            },
+            false => None,
+        };
+
         trace!(
             " NUM: generating {} parameters for function: {:?}",
             num_params,
@@ -839,14 +2543,14 @@ impl MoveSmith {
         );
         for _ in 0..num_params {
             let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
-            let typ = self.get_random_type(u, parent_scope, true, false, true, false, true)?;
+            let typ = self.get_random_type(u, parent_scope, true, false, !entry, false, !entry)?;
             self.env_mut().type_pool.insert_mapping(&name, &typ);
             parameters.push((name, typ));
         }
 
         // More chance to have return type than not
         // so that we can compare the the return value
-        let return_type = match u.int_in_range(0..=10)? > 2 {
+        let mut return_type = match !entry && u.int_in_range(0..=10)? > 2 {
             true => Some(self.get_random_type(u, parent_scope, true, false, true, false, true)?),
             false => None,
         };
@@ -870,22 +2574,162 @@ impl MoveSmith {
             }
         }
 
+        // Occasionally borrow into the arguments: take a few `&mut` parameters
+        // of the same type and return a reference derived from one of them,
+        // so that callers build chains of borrows through several calls
+        let percent = self.env().config.borrow_func_percent.min(100);
+        if !entry && percent > 0 && u.ratio(percent, 100)? {
+            let mut targets = vec![Type::U8, Type::U64, Type::Bool];
+            targets.extend(ref_structs.iter().cloned());
+            let typ = u.choose(&targets)?.clone();
+            for _ in 0..u.int_in_range(1..=3)? {
+                let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+                let param_typ = Type::MutRef(Box::new(typ.clone()));
+                self.env_mut().type_pool.insert_mapping(&name, &param_typ);
+                parameters.push((name, param_typ));
+            }
+            return_type = match bool::arbitrary(u)? {
+                true => Some(Type::MutRef(Box::new(typ))),
+                false => Some(Type::Ref(Box::new(typ))),
+            };
+        }
+
+        // Occasionally take a parameter that must be consumed exactly once.
+        // It is either a linear struct, or a type parameter without copy or drop.
+        // A type parameter without drop then becomes the return type so that
+        // the body can always give the value back.
+        let percent = self.env().config.linear_param_percent.min(100);
+        if !entry && percent > 0 && u.ratio(percent, 100)? {
+            let typ = match linear_structs.is_empty() || bool::arbitrary(u)? {
+                true => {
+                    let linear_tps = type_parameters
+                        .iter()
+                        .filter(|tp| self.is_linear(&Type::TypeParameter((*tp).clone())))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    let tp = match linear_tps.is_empty() {
+                        true => {
+                            let tp = self.generate_type_parameter(
+                                u,
+                                parent_scope,
+                                false,
+                                None,
+                                Some(vec![Ability::Copy]),
+                            )?;
+                            type_parameters.push(tp.clone());
+                            tp
+                        },
+                        false => u.choose(&linear_tps)?.clone(),
+                    };
+                    if !tp.abilities.contains(&Ability::Drop) {
+                        return_type = Some(Type::TypeParameter(tp.clone()));
+                    }
+                    Type::TypeParameter(tp)
+                },
+                false => u.choose(linear_structs)?.clone(),
+            };
+            let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+            self.env_mut().type_pool.insert_mapping(&name, &typ);
+            parameters.push((name, typ));
+        }
+
+        // The other linear type parameters are used by parameters as well.
+        // Values with drop are taken by value and consumed by the body.
+        // Only one value without drop can be returned, so the others are
+        // taken by reference, which the body must not copy from.
+        for tp in type_parameters.clone() {
+            let typ = Type::TypeParameter(tp.clone());
+            if !self.is_linear(&typ) || parameters.iter().any(|(_, t)| t.contains_type(&typ)) {
+                continue;
+            }
+            let param_typ = match tp.abilities.contains(&Ability::Drop) {
+                true => typ,
+                false => match bool::arbitrary(u)? {
+                    true => Type::Ref(Box::new(typ)),
+                    false => Type::MutRef(Box::new(typ)),
+                },
+            };
+            let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+            self.env_mut().type_pool.insert_mapping(&name, &param_typ);
+            parameters.push((name, param_typ));
+        }
+
         let mut inline = false;
-        if !self.env_mut().reached_inline_function_limit(u) && bool::arbitrary(u)? {
+        if !entry
+            && fuel.is_none()
+            && !self.env_mut().reached_inline_function_limit(u)
+            && bool::arbitrary(u)?
+        {
             inline = true;
             self.env_mut().inc_inline_func_counter();
         }
 
-        Ok(FunctionSignature {
-            inline,
-            type_parameters: TypeParameters { type_parameters },
+        // Only inline functions can take lambdas as parameters,
+        // while the other functions can take function values in Move 2
+        let percent = match self.allow_move2() && !entry {
+            true => self.env().config.function_value_param_percent.min(100),
+            false => 0,
+        };
+        if inline {
+            for _ in 0..u.int_in_range(0..=2)? {
+                let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+                let typ = Type::Lambda(self.generate_lambda_type(u, parent_scope)?);
+                self.env_mut().type_pool.insert_mapping(&name, &typ);
+                parameters.push((name, typ));
+            }
+        } else if percent > 0 && u.ratio(percent, 100)? {
+            let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+            let typ = Type::Function(self.generate_function_type(u, parent_scope)?);
+            self.env_mut().type_pool.insert_mapping(&name, &typ);
+            parameters.push((name, typ));
+        }
+
+        // Occasionally take a `self` reference first, so that the function can
+        // be called with the receiver syntax. `self` is not registered in scope,
+        // since the names of variables must be unique, so the body does not use it.
+        let percent = match self.allow_move2() && !entry {
+            true => self.env().config.self_param_percent.min(100),
+            false => 0,
+        };
+        if !ref_structs.is_empty() && percent > 0 && u.ratio(percent, 100)? {
+            let st = Box::new(u.choose(ref_structs)?.clone());
+            let typ = match bool::arbitrary(u)? {
+                true => Type::Ref(st),
+                false => Type::MutRef(st),
+            };
+            parameters.insert(0, (Identifier::new_str("self", IDKinds::Var), typ));
+        }
+
+        Ok(FunctionSignature {
+            inline,
+            type_parameters: TypeParameters { type_parameters },
             name,
             parameters,
             return_type,
+            fuel,
             acquires: BTreeSet::new(),
         })
     }
 
+    /// Generate the type of a lambda parameter with up to two parameters.
+    /// Only basic types are used so that the lambda type is always valid
+    /// in the scope of the function.
+    fn generate_lambda_type(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<LambdaType> {
+        let mut params = Vec::new();
+        for _ in 0..u.int_in_range(0..=2)? {
+            params.push(self.get_random_type(u, parent_scope, true, false, false, false, false)?);
+        }
+        let ret = self.get_random_type(u, parent_scope, true, false, false, false, false)?;
+        Ok(LambdaType {
+            params,
+            ret: Box::new(ret),
+        })
+    }
+
     /// Generate a type parameter with random abilities.
     /// Abilities in `include` will always be included.
     /// Abilities in `exclude` will not be used.
@@ -955,8 +2799,23 @@ impl MoveSmith {
         let (name, block_scope) = self.get_next_identifier(IDKinds::Block, parent_scope);
         trace!("Created block scope: {:?}", block_scope);
 
+        // The body of a function owns the linear parameters of the function
+        if parent_scope.to_identifier().map(|id| id.kind) == Some(IDKinds::Function) {
+            // Nothing is known about the global storage when the function starts
+            self.env_mut().storage.reset(Some(block_scope.clone()));
+            let params = match &self.env().curr_func_signature {
+                Some(sig) => sig.parameters.clone(),
+                None => vec![],
+            };
+            for (param, typ) in params.iter() {
+                if self.is_linear(typ) {
+                    self.env_mut().live_vars.mark_alive(&block_scope, param);
+                }
+            }
+        }
+
         let reach_limit = self.env().expr_depth.will_reached_depth_limit(1);
-        let stmts = if reach_limit {
+        let mut stmts = if reach_limit {
             warn!("Max expr depth will be reached in this block, skipping generating body");
             Vec::new()
         } else {
@@ -965,9 +2824,44 @@ impl MoveSmith {
             trace!("Generating {} statements for block", num_stmts);
             self.generate_statements(u, &block_scope, num_stmts)?
         };
-        let return_expr = match ret_typ {
-            Some(ref typ) => Some(self.generate_block_return(u, &block_scope, typ)?),
-            None => None,
+
+        // Every linear value owned by this block must be consumed before it ends.
+        // A linear return value must be moved out of a variable owned by this block,
+        // so the first one of the return type is set aside instead.
+        // Consuming a value may bind the linear values it holds, e.g. when
+        // unpacking a struct, which are then consumed in turn.
+        let mut linear_ret = None;
+        let mut owned = self.get_owned_linear_vars(&block_scope);
+        while let Some(var) = owned.pop() {
+            let typ = self.env().type_pool.get_type(&var);
+            if linear_ret.is_none() && ret_typ.is_some() && typ == ret_typ {
+                self.env_mut().live_vars.mark_moved(&block_scope, &var);
+                linear_ret = Some(var);
+            } else if let Some(consume) =
+                self.generate_linear_consumption(u, &block_scope, &var, false)?
+            {
+                stmts.extend(consume);
+            } else {
+                // Nothing can consume the value, so the block aborts instead
+                for var in owned.drain(..).chain([var]) {
+                    self.env_mut().live_vars.mark_moved(&block_scope, &var);
+                }
+                let abort = self.generate_abort(u, &block_scope, Some(112233))?;
+                stmts.push(Statement::Expr(abort));
+                break;
+            }
+            if owned.is_empty() {
+                owned = self.get_owned_linear_vars(&block_scope);
+            }
+        }
+
+        let return_expr = match (linear_ret, ret_typ) {
+            (Some(var), _) => Some(Expression::Variable(VariableAccess {
+                name: var,
+                copy: false,
+            })),
+            (None, Some(ref typ)) => Some(self.generate_block_return(u, &block_scope, typ)?),
+            (None, None) => None,
         };
         trace!("Done generating block: {:?}", block_scope);
         Ok(Block {
@@ -985,6 +2879,11 @@ impl MoveSmith {
         parent_scope: &Scope,
         typ: &Type,
     ) -> Result<Expression> {
+        if typ.is_some_ref() {
+            if let Some(expr) = self.generate_returned_borrow(u, parent_scope, typ)? {
+                return Ok(expr);
+            }
+        }
         let var_acc = self.generate_varible_access(u, parent_scope, false, Some(typ))?;
         match var_acc {
             Some(va) => Ok(Expression::Variable(va)),
@@ -992,6 +2891,203 @@ impl MoveSmith {
         }
     }
 
+    /// Generate a reference of type `typ` returned from a block.
+    ///
+    /// A returned reference may have to outlive the function, so it is only
+    /// derived from the reference parameters of the function: a parameter,
+    /// a reborrow or freeze of one, a choice between them, or a call passing
+    /// them on to another function returning a reference.
+    /// Returns `None` if there is no parameter to borrow from.
+    fn generate_returned_borrow(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Result<Option<Expression>> {
+        let params = match &self.env().curr_func_signature {
+            Some(sig) => sig.parameters.clone(),
+            None => return Ok(None),
+        };
+        let sources = self.get_borrow_sources(parent_scope, typ, Some(&params));
+        if sources.is_empty() {
+            return Ok(None);
+        }
+
+        // Calls with fuel fall back to a fresh value once the fuel runs out,
+        // and type arguments could change the type of the returned reference.
+        // Each reference argument needs its own parameter to borrow from.
+        let callees = self
+            .get_callable_functions(parent_scope)
+            .into_iter()
+            .filter(|f| f.return_type.as_ref() == Some(typ))
+            .filter(|f| f.type_parameters.type_parameters.is_empty())
+            .filter(|f| self.get_fuel_to_pass(parent_scope, f).is_none())
+            .filter(|f| {
+                let candidates = self.get_borrow_sources_of_args(parent_scope, f, &params);
+                Self::assign_distinct_sources(&candidates, &mut Vec::new())
+            })
+            .collect::<Vec<FunctionSignature>>();
+
+        let can_nest = !self.env().expr_depth.reached_depth_limit();
+        let weights = [
+            3,                            // Parameter
+            2,                            // Reborrow
+            if can_nest { 1 } else { 0 }, // If-Else
+            if can_nest && !callees.is_empty() {
+                3
+            } else {
+                0
+            }, // Call
+        ];
+        let expr = match choose_idx_weighted(u, &weights)? {
+            0 => Expression::Variable(VariableAccess {
+                name: u.choose(&sources)?.clone(),
+                copy: false,
+            }),
+            1 => {
+                let source = u.choose(&sources)?.clone();
+                self.generate_reborrow(u, &source, typ)?
+            },
+            2 => {
+                self.env_mut().expr_depth.increase_depth();
+                let if_else = self.generate_if(u, parent_scope, Some(typ.clone()))?;
+                self.env_mut().expr_depth.decrease_depth();
+                Expression::IfElse(Box::new(if_else))
+            },
+            3 => {
+                self.env_mut().expr_depth.increase_depth();
+                let callee = u.choose(&callees)?;
+                let mut call =
+                    self.generate_call_to_function(u, parent_scope, callee, Some(typ), true)?;
+                self.env_mut().expr_depth.decrease_depth();
+                // Pass on a different parameter for each reference argument
+                // since the same `&mut` cannot be passed twice
+                let mut candidates = self.get_borrow_sources_of_args(parent_scope, callee, &params);
+                for (_, sources) in candidates.iter_mut() {
+                    for i in (1..sources.len()).rev() {
+                        sources.swap(i, u.choose_index(i + 1)?);
+                    }
+                }
+                // The callees are filtered to those with enough distinct sources
+                let mut assigned = Vec::new();
+                Self::assign_distinct_sources(&candidates, &mut assigned);
+                for ((idx, _), source) in candidates.iter().zip(assigned) {
+                    let ptyp = &callee.parameters[*idx].1;
+                    call.args[*idx] = match bool::arbitrary(u)? {
+                        true => self.generate_reborrow(u, &source, ptyp)?,
+                        false => Expression::Variable(VariableAccess {
+                            name: source,
+                            copy: false,
+                        }),
+                    };
+                }
+                Expression::FunctionCall(call)
+            },
+            _ => panic!("Invalid option for returned borrow"),
+        };
+        Ok(Some(expr))
+    }
+
+    /// Get the parameters of the current function that each reference argument
+    /// of `callee` can be borrowed from, along with the index of the argument.
+    /// `&signer` arguments are not borrowed from the parameters.
+    fn get_borrow_sources_of_args(
+        &self,
+        parent_scope: &Scope,
+        callee: &FunctionSignature,
+        params: &[(Identifier, Type)],
+    ) -> Vec<(usize, Vec<Identifier>)> {
+        callee
+            .parameters
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, ptyp))| {
+                ptyp.is_some_ref() && ptyp != &Type::Ref(Box::new(Type::Signer))
+            })
+            .map(|(idx, (_, ptyp))| {
+                (
+                    idx,
+                    self.get_borrow_sources(parent_scope, ptyp, Some(params)),
+                )
+            })
+            .collect()
+    }
+
+    /// Assign a distinct source to each argument, trying the sources in order.
+    /// Returns false if there are not enough distinct sources.
+    fn assign_distinct_sources(
+        candidates: &[(usize, Vec<Identifier>)],
+        assigned: &mut Vec<Identifier>,
+    ) -> bool {
+        let sources = match candidates.get(assigned.len()) {
+            Some((_, sources)) => sources,
+            None => return true,
+        };
+        for source in sources {
+            if assigned.contains(source) {
+                continue;
+            }
+            assigned.push(source.clone());
+            if Self::assign_distinct_sources(candidates, assigned) {
+                return true;
+            }
+            assigned.pop();
+        }
+        false
+    }
+
+    /// Get the variables a reference of type `typ` can be derived from.
+    /// A `&T` can also be derived from a `&mut T` by freezing it.
+    /// If `params` is given, only these parameters of the function are considered.
+    fn get_borrow_sources(
+        &self,
+        parent_scope: &Scope,
+        typ: &Type,
+        params: Option<&[(Identifier, Type)]>,
+    ) -> Vec<Identifier> {
+        let mut source_types = vec![];
+        match typ {
+            Type::MutRef(_) => source_types.push(typ.clone()),
+            Type::Ref(inner) => {
+                source_types.push(typ.clone());
+                source_types.push(Type::MutRef(inner.clone()));
+            },
+            _ => (),
+        };
+        source_types
+            .iter()
+            .flat_map(|t| self.env().live_variables(parent_scope, Some(t)))
+            .filter(|id| params.is_none_or(|ps| ps.iter().any(|(p, _)| p == id)))
+            .collect()
+    }
+
+    /// Derive a reference of type `typ` from the reference variable `source`.
+    ///
+    /// A `&mut T` is reborrowed as `&mut *source`, while a `&T` is either a
+    /// reborrow, or an implicit or explicit `freeze` of a `&mut T`.
+    fn generate_reborrow(
+        &self,
+        u: &mut Unstructured,
+        source: &Identifier,
+        typ: &Type,
+    ) -> Result<Expression> {
+        let var = Expression::Variable(VariableAccess {
+            name: source.clone(),
+            copy: false,
+        });
+        let source_typ = self.env().type_pool.get_type(source).unwrap();
+        let deref = Expression::Dereference(Box::new(var.clone()));
+        Ok(match (typ, source_typ.is_mut_ref()) {
+            (Type::MutRef(_), _) => Expression::MutReference(Box::new(deref)),
+            (_, true) => match u.int_in_range(0..=2)? {
+                0 => Expression::Freeze(Box::new(var)),
+                1 => var,
+                _ => Expression::Reference(Box::new(deref)),
+            },
+            (_, false) => Expression::Reference(Box::new(deref)),
+        })
+    }
+
     /// Generate a list of statements.
     fn generate_statements(
         &self,
@@ -1020,44 +3116,849 @@ impl MoveSmith {
                 stmts.extend(self.generate_statement(u, parent_scope)?);
             }
 
-            trace!("Done generating statement #{}", i + 1);
+            trace!("Done generating statement #{}", i + 1);
+        }
+        Ok(stmts)
+    }
+
+    fn generate_additional_operation(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<Statement> {
+        Ok(match bool::arbitrary(u)? {
+            true => self.generate_resource_operation(u, parent_scope)?,
+            false => self.generate_vector_operation(u, parent_scope)?,
+        })
+    }
+
+    /// Generate a random statement.
+    fn generate_statement(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<Vec<Statement>> {
+        // Only start a new loop if we can still nest one more level
+        // and the loop body will not be empty
+        let loop_weight = match self.env().reached_loop_depth_limit()
+            || self.env().expr_depth.will_reached_depth_limit(1)
+        {
+            true => 0,
+            false => 2,
+        };
+
+        // `break` and `continue` can only appear inside a loop,
+        // and cannot skip the consumption of linear values
+        let jump_weight = match self.env().in_loop() && !self.has_live_linear_vars(parent_scope) {
+            true => 1,
+            false => 0,
+        };
+
+        // Unpacking requires a struct type in scope
+        let unpack_weight = match self
+            .env()
+            .get_identifiers(None, Some(IDKinds::Struct), Some(parent_scope))
+            .is_empty()
+        {
+            true => 0,
+            false => 2,
+        };
+
+        // Linear values can be created if a linear struct is in scope
+        let linear_decl_weight = match self.get_linear_structs(parent_scope).is_empty() {
+            true => 0,
+            false => 2,
+        };
+
+        // Linear values can only be consumed by the block owning them
+        let owned_linear_vars = self.get_owned_linear_vars(parent_scope);
+        let linear_consume_weight = match owned_linear_vars.is_empty() {
+            true => 0,
+            false => 2,
+        };
+
+        // Function values are only supported by Move 2
+        let function_value_weight = match self.allow_move2() {
+            true => 2,
+            false => 0,
+        };
+
+        let weights = vec![
+            6,                     // Declaration
+            4,                     // Expression
+            6,                     // Vector operation
+            loop_weight,           // Loop --> 0 or 2
+            jump_weight,           // Break/Continue --> 0 or 1
+            unpack_weight,         // Unpack --> 0 or 2
+            linear_decl_weight,    // Linear declaration --> 0 or 2
+            linear_consume_weight, // Linear consumption --> 0 or 2
+            2,                     // Assert
+            function_value_weight, // Function value declaration --> 0 or 2
+        ];
+        let idx = choose_idx_weighted(u, &weights)?;
+        Ok(match idx {
+            0 => {
+                let dec = self.generate_declaration(u, parent_scope)?;
+                let record = self.generate_record_value_expr(&dec.names[0]);
+                let mut stmts = vec![Statement::Decl(dec)];
+                stmts.extend(record.map(Statement::Expr));
+                stmts
+            },
+            1 => self
+                .generate_expression(u, parent_scope)?
+                .into_iter()
+                .map(Statement::Expr)
+                .collect(),
+            2 => vec![self.generate_vector_operation(u, parent_scope)?],
+            3 => self.generate_loop(u, parent_scope)?,
+            4 => vec![self.generate_loop_jump(u, parent_scope)?],
+            5 => self.generate_unpack(u, parent_scope)?,
+            6 => self.generate_linear_declaration(u, parent_scope)?,
+            7 => {
+                let var = u.choose(&owned_linear_vars)?;
+                self.generate_linear_consumption(u, parent_scope, var, true)?
+                    .unwrap_or_default()
+            },
+            8 => vec![Statement::Expr(self.generate_assert(u, parent_scope)?)],
+            9 => vec![self.generate_function_value_declaration(u, parent_scope)?],
+            _ => panic!("Invalid statement type"),
+        })
+    }
+
+    /// Generate a `let` statement that destructures a struct value,
+    /// followed by recording the values of all newly bound variables.
+    ///
+    /// The value is generated before the pattern so that it cannot refer to
+    /// the variables bound by the pattern.
+    fn generate_unpack(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<Vec<Statement>> {
+        let mut struct_ids =
+            self.env()
+                .get_identifiers(None, Some(IDKinds::Struct), Some(parent_scope));
+        // Linear values are unpacked when they are consumed
+        struct_ids.retain(|id| !self.is_linear(&Type::new_struct(id, None)));
+        let chosen = u.choose(&struct_ids)?;
+        let mut typ = self
+            .get_struct_definition_with_identifier(chosen)
+            .unwrap()
+            .get_type();
+        if self.is_type_concretizable(&typ, parent_scope) {
+            typ = self
+                .concretize_type(u, &typ, parent_scope, vec![], None)
+                .unwrap();
+        }
+        trace!("Generating unpack of type: {:?}", typ.inline());
+
+        let value = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
+        let mut bound = Vec::new();
+        let pattern = self.generate_struct_pattern(u, parent_scope, &typ, &mut bound)?;
+
+        let mut stmts = vec![Statement::Unpack(Unpack { pattern, value })];
+        for var in bound.iter() {
+            stmts.extend(self.generate_record_value_expr(var).map(Statement::Expr));
+        }
+        Ok(stmts)
+    }
+
+    /// Generate a `let` statement that binds a freshly packed linear struct,
+    /// followed by recording the value.
+    ///
+    /// Generic structs are instantiated with type arguments that have copy and drop.
+    /// The value may also be passed through a generic function returning its
+    /// linear type parameter, which is then instantiated with the struct type.
+    ///
+    /// The new variable is owned by `parent_scope` and must be consumed
+    /// before the block ends.
+    fn generate_linear_declaration(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<Vec<Statement>> {
+        let struct_ids = self.get_linear_structs(parent_scope);
+        let chosen = u.choose(&struct_ids)?;
+        let mut typ = self
+            .get_struct_definition_with_identifier(chosen)
+            .unwrap()
+            .get_type();
+        if self.is_type_concretizable(&typ, parent_scope) {
+            typ = self
+                .concretize_type(
+                    u,
+                    &typ,
+                    parent_scope,
+                    vec![Ability::Copy, Ability::Drop],
+                    None,
+                )
+                .unwrap();
+        }
+        trace!("Generating linear declaration of type: {:?}", typ.inline());
+
+        let producers = self.get_linear_producers(parent_scope, &typ);
+        let value = match producers.is_empty() || bool::arbitrary(u)? {
+            true => self.generate_expression_of_type(u, parent_scope, &typ, true, true)?,
+            false => {
+                let (func, tp) = u.choose(&producers)?.clone();
+                let desired_types = func
+                    .type_parameters
+                    .type_parameters
+                    .iter()
+                    .map(|p| (p == &tp).then(|| typ.clone()))
+                    .collect();
+                let call = self.generate_call_with_type_args(
+                    u,
+                    parent_scope,
+                    &func,
+                    desired_types,
+                    true,
+                    None,
+                )?;
+                Expression::FunctionCall(call)
+            },
+        };
+        let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+        self.env_mut().type_pool.insert_mapping(&name, &typ);
+        self.env_mut().live_vars.mark_alive(parent_scope, &name);
+
+        let record = self.generate_record_value_expr(&name);
+        let mut stmts = vec![Statement::Decl(Declaration {
+            typs: vec![typ],
+            names: vec![name],
+            value: Some(value),
+            emit_type: true,
+        })];
+        stmts.extend(record.map(Statement::Expr));
+        Ok(stmts)
+    }
+
+    /// Get the functions callable from `parent_scope` that return a value of
+    /// their linear type parameter, which can be instantiated with `typ`.
+    /// The type parameter must not appear in the parameters other than by value,
+    /// so that the arguments of the type are values of `typ` packed by the caller.
+    fn get_linear_producers(
+        &self,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Vec<(FunctionSignature, TypeParameter)> {
+        let abilities = self.derive_abilities_of_type(typ);
+        self.get_callable_functions(parent_scope)
+            .into_iter()
+            .filter(|f| self.get_fuel_to_pass(parent_scope, f).is_none())
+            .filter_map(|f| {
+                let ret = f.return_type.clone()?;
+                let tp = match &ret {
+                    Type::TypeParameter(tp) if self.is_linear(&ret) => tp.clone(),
+                    _ => return None,
+                };
+                let usable = tp.abilities.iter().all(|a| abilities.contains(a))
+                    && f.parameters
+                        .iter()
+                        .all(|(_, t)| t == &ret || !t.contains_type(&ret));
+                usable.then_some((f, tp))
+            })
+            .collect()
+    }
+
+    /// Generate statements that consume the linear variable `var`
+    /// owned by `parent_scope`. The value is either:
+    /// * unpacked with a struct pattern
+    /// * moved to global storage, or unpacked if the resource already exists
+    /// * passed by value to a function taking a parameter of its type, or of a
+    ///   type parameter that is instantiated with its type
+    /// * passed through a generic function that returns it, binding the result
+    ///   to a new variable owned by `parent_scope` (only if `allow_pass_through`)
+    /// * moved to a new variable owned by `parent_scope` (only if `allow_pass_through`)
+    /// * packed into a generic linear struct bound to a new variable owned by
+    ///   `parent_scope` (only if `allow_pass_through`)
+    /// * dropped with `let _ = var;` if its type has drop
+    ///
+    /// Values of type parameters without drop may have no option available,
+    /// in which case `None` is returned.
+    fn generate_linear_consumption(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        var: &Identifier,
+        allow_pass_through: bool,
+    ) -> Result<Option<Vec<Statement>>> {
+        let typ = self.env().type_pool.get_type(var).unwrap();
+        let is_struct = !typ.is_type_parameter();
+        let abilities = self.derive_abilities_of_type(&typ);
+        let has_key = abilities.contains(&Ability::Key);
+        let has_drop = abilities.contains(&Ability::Drop);
+
+        // A call passing on fuel is conditional, so it cannot consume the value
+        let callables = self
+            .get_callable_functions(parent_scope)
+            .into_iter()
+            .filter(|f| self.get_fuel_to_pass(parent_scope, f).is_none())
+            .collect::<Vec<_>>();
+        // Functions taking a parameter of exactly this type, or a parameter of
+        // a type parameter that is only used by this parameter
+        let takers = callables
+            .iter()
+            .filter_map(|f| {
+                let no_types = vec![None; f.type_parameters.type_parameters.len()];
+                if let Some(idx) = f.parameters.iter().position(|(_, t)| t == &typ) {
+                    return is_struct.then(|| (f.clone(), idx, no_types));
+                }
+                f.type_parameters
+                    .type_parameters
+                    .iter()
+                    .enumerate()
+                    .find_map(|(tp_idx, tp)| {
+                        let tp_typ = Type::TypeParameter(tp.clone());
+                        let mut users = f
+                            .parameters
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, (_, t))| t.contains_type(&tp_typ));
+                        let (idx, (_, param_typ)) = users.next()?;
+                        let usable = param_typ == &tp_typ
+                            && users.next().is_none()
+                            && !f
+                                .return_type
+                                .as_ref()
+                                .is_some_and(|r| r.contains_type(&tp_typ))
+                            && tp.abilities.iter().all(|a| abilities.contains(a));
+                        let mut desired_types = no_types.clone();
+                        desired_types[tp_idx] = Some(typ.clone());
+                        usable.then_some((f.clone(), idx, desired_types))
+                    })
+            })
+            .collect::<Vec<_>>();
+        // Functions returning the value of their linear type parameter
+        let passers = callables
+            .iter()
+            .filter_map(|f| {
+                let ret = f.return_type.clone()?;
+                let constraints = match &ret {
+                    Type::TypeParameter(tp) if self.is_linear(&ret) => tp.abilities.clone(),
+                    _ => return None,
+                };
+                if !constraints.iter().all(|a| abilities.contains(a)) {
+                    return None;
+                }
+                let idx = f.parameters.iter().position(|(_, t)| t == &ret)?;
+                Some((f.clone(), idx))
+            })
+            .collect::<Vec<_>>();
+        // Generic linear structs whose type parameter can be instantiated with
+        // this type. Generic struct values are not wrapped again.
+        let wrappers = match typ {
+            Type::Struct(_) | Type::TypeParameter(_) => self
+                .get_linear_structs(parent_scope)
+                .into_iter()
+                .filter(|id| {
+                    let st = self.get_struct_definition_with_identifier(id).unwrap();
+                    let tps = &st.type_parameters.type_parameters;
+                    tps.len() == 1 && tps[0].abilities.iter().all(|a| abilities.contains(a))
+                })
+                .collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        // The existing resource is discarded by `move_to` if all fields have drop
+        let can_discard = self.get_struct_fields_of_type(&typ).is_some_and(|fields| {
+            fields
+                .iter()
+                .all(|(_, t)| self.derive_abilities_of_type(t).contains(&Ability::Drop))
+        });
+
+        let mut choices = vec![
+            ("unpack", if is_struct { 3 } else { 0 }),
+            ("move_to", if has_key && can_discard { 2 } else { 0 }),
+            ("call", if takers.is_empty() { 0 } else { 3 }),
+            (
+                "pass_through",
+                match allow_pass_through && !passers.is_empty() {
+                    true => 2,
+                    false => 0,
+                },
+            ),
+            ("rebind", if allow_pass_through { 1 } else { 0 }),
+            ("wrap", match allow_pass_through && !wrappers.is_empty() {
+                true => 2,
+                false => 0,
+            }),
+            ("drop", if has_drop { 1 } else { 0 }),
+        ];
+        choices.retain(|(_, w)| *w > 0);
+        if choices.is_empty() {
+            return Ok(None);
+        }
+        let choice = choose_item_weighted(u, &choices)?;
+        trace!("Consuming linear variable {:?} by {}", var.inline(), choice);
+
+        // The value is moved first so that the other generated parts
+        // cannot borrow or use it
+        self.env_mut().live_vars.mark_moved(parent_scope, var);
+        let moved = Expression::Variable(VariableAccess {
+            name: var.clone(),
+            copy: false,
+        });
+
+        let stmts = match choice {
+            "unpack" => {
+                let mut bound = Vec::new();
+                let pattern = self.generate_struct_pattern(u, parent_scope, &typ, &mut bound)?;
+                let mut stmts = vec![Statement::Unpack(Unpack {
+                    pattern,
+                    value: moved,
+                })];
+                for var in bound.iter() {
+                    stmts.extend(self.generate_record_value_expr(var).map(Statement::Expr));
+                }
+                stmts
+            },
+            "move_to" => {
+                let name = match &typ {
+                    Type::Struct(st) => st.name.clone(),
+                    Type::StructConcrete(st) => st.name.clone(),
+                    _ => panic!("Only structs can be moved to global storage"),
+                };
+                let signer = self.generate_signer_ref(u, parent_scope)?;
+                let addr = self.generate_address_of(signer.clone());
+                self.update_storage_model(parent_scope, &typ, &addr, true);
+                let check_exists = Expression::Resource(ResourceOperation {
+                    kind: ResourceOperationKind::Exists,
+                    typ: typ.clone(),
+                    args: vec![addr],
+                    index_notation: false,
+                });
+                let move_to = Expression::Resource(ResourceOperation {
+                    kind: ResourceOperationKind::MoveTo,
+                    typ: typ.clone(),
+                    args: vec![signer, moved.clone()],
+                    index_notation: false,
+                });
+                // The resource may already exist, in which case the value is discarded
+                let discard = Statement::Unpack(Unpack {
+                    pattern: self.generate_discard_pattern(&name),
+                    value: moved,
+                });
+                let block_name = Identifier::new_str("_exist_check", IDKinds::Block);
+                vec![Statement::Expr(Expression::IfElse(Box::new(IfExpr {
+                    condition: Expression::UnaryOperation(UnaryOperation::Not(Box::new(
+                        check_exists,
+                    ))),
+                    body: Block {
+                        name: block_name.clone(),
+                        stmts: vec![Statement::Expr(move_to)],
+                        return_expr: None,
+                    },
+                    else_expr: Some(ElseExpr {
+                        typ: None,
+                        body: Block {
+                            name: block_name,
+                            stmts: vec![discard],
+                            return_expr: None,
+                        },
+                    }),
+                })))]
+            },
+            "call" => {
+                let (func, idx, desired_types) = u.choose(&takers)?.clone();
+                let call = self.generate_call_with_type_args(
+                    u,
+                    parent_scope,
+                    &func,
+                    desired_types,
+                    true,
+                    Some((idx, moved)),
+                )?;
+                vec![Statement::Expr(Expression::FunctionCall(call))]
+            },
+            "pass_through" => {
+                let (func, idx) = u.choose(&passers)?.clone();
+                let ret = func.return_type.clone().unwrap();
+                let desired_types = func
+                    .type_parameters
+                    .type_parameters
+                    .iter()
+                    .map(|tp| match Type::TypeParameter(tp.clone()) == ret {
+                        true => Some(typ.clone()),
+                        false => None,
+                    })
+                    .collect();
+                let call = self.generate_call_with_type_args(
+                    u,
+                    parent_scope,
+                    &func,
+                    desired_types,
+                    true,
+                    Some((idx, moved)),
+                )?;
+                self.generate_linear_rebinding(parent_scope, &typ, Expression::FunctionCall(call))
+            },
+            "rebind" => self.generate_linear_rebinding(parent_scope, &typ, moved),
+            "wrap" => {
+                let name = u.choose(&wrappers)?.clone();
+                let type_args = TypeArgs {
+                    type_args: vec![typ.clone()],
+                };
+                let wrapped = Type::new_concrete_struct(&name, Some(&type_args));
+                let positional = self
+                    .get_struct_definition_with_identifier(&name)
+                    .unwrap()
+                    .positional;
+                let mut fields = Vec::new();
+                let mut moved = Some(moved);
+                for (field, field_typ) in self.get_struct_fields_of_type(&wrapped).unwrap() {
+                    let expr = match field_typ == typ {
+                        true => moved.take().unwrap(),
+                        false => self.generate_expression_of_type(
+                            u,
+                            parent_scope,
+                            &field_typ,
+                            true,
+                            true,
+                        )?,
+                    };
+                    fields.push((field, expr));
+                }
+                let pack = Expression::StructPack(StructPack {
+                    name,
+                    type_args,
+                    positional,
+                    fields,
+                });
+                self.generate_linear_rebinding(parent_scope, &wrapped, pack)
+            },
+            "drop" => vec![Statement::Decl(Declaration {
+                typs: vec![typ.clone()],
+                names: vec![Identifier::new_str("_", IDKinds::Var)],
+                value: Some(moved),
+                emit_type: bool::arbitrary(u)?,
+            })],
+            _ => panic!("Invalid linear consumption"),
+        };
+        Ok(Some(stmts))
+    }
+
+    /// Generate a `let` statement binding `value` of the linear type `typ`
+    /// to a new variable owned by `parent_scope`, followed by recording the value.
+    fn generate_linear_rebinding(
+        &self,
+        parent_scope: &Scope,
+        typ: &Type,
+        value: Expression,
+    ) -> Vec<Statement> {
+        let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+        self.env_mut().type_pool.insert_mapping(&name, typ);
+        self.env_mut().live_vars.mark_alive(parent_scope, &name);
+        let record = self.generate_record_value_expr(&name);
+        let mut stmts = vec![Statement::Decl(Declaration {
+            typs: vec![typ.clone()],
+            names: vec![name],
+            value: Some(value),
+            emit_type: true,
+        })];
+        stmts.extend(record.map(Statement::Expr));
+        stmts
+    }
+
+    /// Generate a pattern of the struct `name` discarding all fields.
+    /// The fields are omitted with `..` in Move 2 and ignored with `_` otherwise.
+    fn generate_discard_pattern(&self, name: &Identifier) -> StructPattern {
+        let st = self.get_struct_definition_with_identifier(name).unwrap();
+        let (fields, has_rest) = match self.allow_move2() {
+            true => (vec![], true),
+            false => (
+                st.fields
+                    .iter()
+                    .map(|(field, _)| (field.clone(), FieldPattern::Wildcard))
+                    .collect(),
+                false,
+            ),
+        };
+        StructPattern {
+            name: name.clone(),
+            type_args: TypeArgs::default(),
+            positional: st.positional,
+            fields,
+            has_rest,
+        }
+    }
+
+    /// Generate a pattern that matches the struct type `typ`.
+    ///
+    /// Each field is either bound to a new variable, ignored with `_`,
+    /// matched by a nested struct pattern, or omitted and covered by `..`.
+    /// The `..` rest pattern is only generated for Move 2.
+    /// For positional structs, all fields after the first omitted one are omitted.
+    /// Newly bound variables are registered in scope and appended to `bound`.
+    fn generate_struct_pattern(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &Type,
+        bound: &mut Vec<Identifier>,
+    ) -> Result<StructPattern> {
+        let (name, type_args) = match typ {
+            Type::Struct(st) => (st.name.clone(), TypeArgs::default()),
+            Type::StructConcrete(st) => (st.name.clone(), st.type_args.clone()),
+            _ => panic!("Cannot generate struct pattern for {:?}", typ),
+        };
+        let fields = self.get_struct_fields_of_type(typ).unwrap();
+        let positional = self
+            .get_struct_definition_with_identifier(&name)
+            .unwrap()
+            .positional;
+
+        let mut field_pats = Vec::new();
+        let mut has_rest = false;
+        for (field, field_typ) in fields {
+            let nested_weight = match self.get_struct_fields_of_type(&field_typ) {
+                Some(_) => 2,
+                None => 0,
+            };
+            // A field without drop must be bound or matched
+            let discard_weight = match self
+                .derive_abilities_of_type(&field_typ)
+                .contains(&Ability::Drop)
+            {
+                true => 1,
+                false => 0,
+            };
+            let weights = [
+                ("var", 6),
+                ("wildcard", discard_weight),
+                ("nested", nested_weight),
+                (
+                    "rest",
+                    if self.allow_move2() {
+                        discard_weight
+                    } else {
+                        0
+                    },
+                ),
+            ];
+            match choose_item_weighted(u, &weights)? {
+                "var" => {
+                    let (var, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+                    self.env_mut().type_pool.insert_mapping(&var, &field_typ);
+                    self.env_mut().live_vars.mark_alive(parent_scope, &var);
+                    bound.push(var.clone());
+                    field_pats.push((field, FieldPattern::Var(var)));
+                },
+                "wildcard" => field_pats.push((field, FieldPattern::Wildcard)),
+                "nested" => {
+                    let nested =
+                        self.generate_struct_pattern(u, parent_scope, &field_typ, bound)?;
+                    field_pats.push((field, FieldPattern::Struct(nested)));
+                },
+                "rest" => {
+                    has_rest = true;
+                    if positional {
+                        break;
+                    }
+                },
+                _ => panic!("Invalid field pattern choice"),
+            }
+        }
+
+        Ok(StructPattern {
+            name,
+            type_args,
+            positional,
+            fields: field_pats,
+            has_rest,
+        })
+    }
+
+    /// Generate a random loop statement: `while`, `loop` or `for`.
+    /// Range `for` loops are only generated for Move 2.
+    fn generate_loop(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<Vec<Statement>> {
+        let max_kind = match self.allow_move2() {
+            true => 2,
+            false => 1,
+        };
+        match u.int_in_range(0..=max_kind)? {
+            0 => self.generate_counted_loop(u, parent_scope, true),
+            1 => self.generate_counted_loop(u, parent_scope, false),
+            2 => Ok(vec![self.generate_for_loop(u, parent_scope)?]),
+            _ => panic!("Invalid loop kind"),
+        }
+    }
+
+    /// Generate a `while` (if `use_while` is set) or a `loop` statement.
+    ///
+    /// Each loop is bounded by a fresh `u64` counter that is incremented at the
+    /// beginning of every iteration, so that the loop always terminates within
+    /// `num_iterations_in_loop` iterations no matter what the body does.
+    /// The counter is deliberately not registered in the type pool so that the
+    /// body can never read or overwrite it.
+    ///
+    /// Returns the declaration of the counter followed by the loop itself.
+    fn generate_counted_loop(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        use_while: bool,
+    ) -> Result<Vec<Statement>> {
+        use NumericalBinaryOperator as OP;
+
+        let (counter, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+        let bound = self.env().config.num_iterations_in_loop.select(u)?;
+        trace!("NUM: generating loop with at most {} iterations", bound);
+
+        let u64_literal = |v: usize| {
+            Expression::NumberLiteral(NumberLiteral {
+                value: BigUint::from(v),
+                typ: Type::U64,
+            })
+        };
+        let counter_var = Expression::Variable(VariableAccess {
+            name: counter.clone(),
+            copy: false,
+        });
+        let compare_counter = |op: OP| {
+            Expression::BinaryOperation(Box::new(BinaryOperation {
+                op: BinaryOperator::Numerical(op),
+                lhs: counter_var.clone(),
+                rhs: u64_literal(bound),
+            }))
+        };
+
+        let counter_decl = Statement::Decl(Declaration {
+            typs: vec![Type::U64],
+            names: vec![counter.clone()],
+            value: Some(u64_literal(0)),
+            emit_type: true,
+        });
+
+        // `counter = counter + 1`
+        let increment = Statement::Expr(Expression::Assign(Box::new(Assignment {
+            lhs: counter_var.clone(),
+            op: None,
+            rhs: Expression::BinaryOperation(Box::new(BinaryOperation {
+                op: BinaryOperator::Numerical(OP::Add),
+                lhs: counter_var.clone(),
+                rhs: u64_literal(1),
+            })),
+        })));
+
+        // The condition is generated outside of the loop so that it never
+        // contains `break` or `continue`
+        let condition = match use_while {
+            true => {
+                let bounded = compare_counter(OP::Le);
+                match bool::arbitrary(u)? {
+                    true => Expression::BinaryOperation(Box::new(BinaryOperation {
+                        op: BinaryOperator::Boolean(BooleanBinaryOperator::And),
+                        lhs: bounded,
+                        rhs: self.generate_expression_of_type(
+                            u,
+                            parent_scope,
+                            &Type::Bool,
+                            true,
+                            true,
+                        )?,
+                    })),
+                    false => bounded,
+                }
+            },
+            false => compare_counter(OP::Geq),
+        };
+
+        // The nesting of loops is bounded by `max_loop_depth` so we do not
+        // increase the expression depth here
+        self.env_mut().enter_loop();
+        let mut body = self.generate_block(u, parent_scope, None, None)?;
+        self.env_mut().exit_loop();
+
+        let stmt = match use_while {
+            // while (counter < bound [&& cond]) { counter = counter + 1; ... }
+            true => {
+                body.stmts.insert(0, increment);
+                Statement::While(While { condition, body })
+            },
+            // loop { if (counter >= bound) { break; }; counter = counter + 1; ... }
+            false => {
+                let exit = Statement::Expr(Expression::IfElse(Box::new(IfExpr {
+                    condition,
+                    body: Block {
+                        name: Identifier::new_str("_loop_exit", IDKinds::Block),
+                        stmts: vec![Statement::Break],
+                        return_expr: None,
+                    },
+                    else_expr: None,
+                })));
+                body.stmts.splice(0..0, [exit, increment]);
+                Statement::Loop(Loop { body })
+            },
+        };
+        Ok(vec![counter_decl, stmt])
+    }
+
+    /// Generate a Move 2 range loop: `for (var in start..end) { ... }`.
+    ///
+    /// The loop owns a hidden block scope in which the loop variable is
+    /// declared, so the variable is only visible inside the body.
+    /// The bounds are literals at most `num_iterations_in_loop` apart and the
+    /// loop variable is read-only, so the loop always terminates.
+    /// Every iteration starts by recording the current value of the loop variable.
+    fn generate_for_loop(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<Statement> {
+        let typ = self.get_random_type(u, parent_scope, false, false, false, false, false)?;
+        let num_iters = self.env().config.num_iterations_in_loop.select(u)?;
+        trace!("NUM: generating for loop with {} iterations", num_iters);
+
+        // Choose the start so that `start + num_iters` cannot overflow
+        let max = (BigUint::from(1u32) << typ.num_bits().unwrap()) - BigUint::from(1u32);
+        let mut start = self.generate_number_literal(u, Some(&typ), None, Some(max - num_iters))?;
+        let mut end = NumberLiteral {
+            value: &start.value + num_iters,
+            typ: typ.clone(),
+        };
+        // Occasionally use a reversed range, which runs zero iterations
+        if u.ratio(1, 10)? {
+            std::mem::swap(&mut start, &mut end);
         }
-        Ok(stmts)
-    }
 
-    fn generate_additional_operation(
-        &self,
-        u: &mut Unstructured,
-        parent_scope: &Scope,
-    ) -> Result<Statement> {
-        Ok(match bool::arbitrary(u)? {
-            true => self.generate_resource_operation(u, parent_scope)?,
-            false => self.generate_vector_operation(u, parent_scope)?,
-        })
+        let (_, loop_scope) = self.get_next_identifier(IDKinds::Block, parent_scope);
+        let (var, _) = self.get_next_identifier(IDKinds::Var, &loop_scope);
+        self.env_mut().type_pool.insert_mapping(&var, &typ);
+        self.env_mut().live_vars.mark_alive(&loop_scope, &var);
+        self.env_mut().mark_read_only(&var);
+
+        self.env_mut().enter_loop();
+        let mut body = self.generate_block(u, &loop_scope, None, None)?;
+        self.env_mut().exit_loop();
+        if let Some(record) = self.generate_record_value_expr(&var) {
+            body.stmts.insert(0, Statement::Expr(record));
+        }
+
+        Ok(Statement::For(For {
+            var,
+            start: Expression::NumberLiteral(start),
+            end: Expression::NumberLiteral(end),
+            body,
+        }))
     }
 
-    /// Generate a random statement.
-    fn generate_statement(
-        &self,
-        u: &mut Unstructured,
-        parent_scope: &Scope,
-    ) -> Result<Vec<Statement>> {
-        let weights = vec![6, 4, 6];
-        let idx = choose_idx_weighted(u, &weights)?;
-        Ok(match idx {
-            0 => {
-                let dec = self.generate_declaration(u, parent_scope)?;
-                let record = self.generate_record_value_expr(&dec.names[0]);
-                vec![Statement::Decl(dec), Statement::Expr(record)]
+    /// Generate a `break` or `continue` guarded by a random condition.
+    ///
+    /// The jump is never generated unconditionally so that the statements
+    /// following it are not dead code.
+    fn generate_loop_jump(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<Statement> {
+        assert!(self.env().in_loop());
+        let jump = match bool::arbitrary(u)? {
+            true => Statement::Break,
+            false => Statement::Continue,
+        };
+        let condition =
+            self.generate_expression_of_type(u, parent_scope, &Type::Bool, true, true)?;
+        Ok(Statement::Expr(Expression::IfElse(Box::new(IfExpr {
+            condition,
+            body: Block {
+                name: Identifier::new_str("_loop_jump", IDKinds::Block),
+                stmts: vec![jump],
+                return_expr: None,
             },
-            1 => self
-                .generate_expression(u, parent_scope)?
-                .into_iter()
-                .map(Statement::Expr)
-                .collect(),
-            2 => vec![self.generate_vector_operation(u, parent_scope)?],
-            _ => panic!("Invalid statement type"),
-        })
+            else_expr: None,
+        }))))
     }
 
     fn generate_new_vector_literal(
@@ -1177,7 +4078,7 @@ impl MoveSmith {
         use Expression as E;
         use VectorOperationKind::*;
 
-        let op = self.random_vector_operation_kind(u)?;
+        let op = self.random_vector_operation_kind(u, parent_scope)?;
         trace!("Generating vector operation: {:?}", op);
 
         let vec_ids = self.env().get_vector_identifiers(None, parent_scope);
@@ -1257,13 +4158,32 @@ impl MoveSmith {
             self.env_mut().expr_depth.reset_max_depth();
         }
 
+        if let Some((param_typs, ret_typ)) = op.lambda_signature(&elem_typ) {
+            // The lambda must not touch the vector being iterated
+            self.env_mut().hide_var(&vec_id);
+            let lambda = self.generate_lambda(u, parent_scope, &param_typs, ret_typ)?;
+            self.env_mut().unhide_var(&vec_id);
+            args.push(E::Lambda(Box::new(lambda)));
+        }
+
         trace!(
             "Generated arguments for vector operation {:?}: {:?}",
             op,
             args
         );
 
+        // Receiver style calls and index notation are only supported by Move 2.
+        // A copyable element can also be read by value with `v[i]`.
+        let sugar = self.allow_move2() && bool::arbitrary(u)?;
+        let by_value = sugar
+            && matches!(op, Borrow)
+            && self
+                .derive_abilities_of_type(&elem_typ)
+                .contains(&Ability::Copy)
+            && bool::arbitrary(u)?;
+
         let ret_typs = match op.ret_type(&elem_typ) {
+            _ if by_value => vec![elem_typ.clone()],
             None => vec![],
             Some(Type::Tuple(typs)) => typs,
             Some(typ) => vec![typ],
@@ -1278,7 +4198,15 @@ impl MoveSmith {
             ret_ids.push(name);
         }
 
-        let vec_expr = E::VectorOperation(VectorOperation { elem_typ, op, args });
+        let mut vec_expr = E::VectorOperation(VectorOperation {
+            elem_typ,
+            op,
+            args,
+            sugar,
+        });
+        if by_value {
+            vec_expr = E::Dereference(Box::new(vec_expr));
+        }
 
         Ok(match ret_ids.is_empty() {
             true => Statement::Expr(vec_expr),
@@ -1291,7 +4219,11 @@ impl MoveSmith {
         })
     }
 
-    fn random_vector_operation_kind(&self, u: &mut Unstructured) -> Result<VectorOperationKind> {
+    fn random_vector_operation_kind(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<VectorOperationKind> {
         use VectorOperationKind::*;
         let op_weights = vec![
             (Empty, 15),
@@ -1310,17 +4242,522 @@ impl MoveSmith {
             (IndexOf, 10),
             (Remove, 5),
             (SwapRemove, 5),
+            (ForEach, 3),
+            (ForEachRef, 3),
+            (ForEachMut, 3),
+            (Fold(Type::U8), 3),
+            (Map(Type::U8), 3),
+            (Filter, 3),
+        ];
+        // The types carried by `Fold` and `Map` are placeholders
+        Ok(match choose_item_weighted(u, &op_weights)? {
+            Fold(_) => Fold(self.get_random_concrete_type(u, parent_scope)?),
+            Map(_) => Map(self.get_random_concrete_type(u, parent_scope)?),
+            op => op,
+        })
+    }
+
+    /// Get a random non-reference type and concretize it if needed.
+    fn get_random_concrete_type(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<Type> {
+        let typ = self.get_random_type(u, parent_scope, true, true, true, true, false)?;
+        Ok(self
+            .concretize_type(u, &typ, parent_scope, vec![], None)
+            .unwrap_or(typ))
+    }
+
+    /// Generate a lambda with the given parameter types.
+    ///
+    /// The parameters live in a new scope together with the body, and the
+    /// body can capture all variables in `parent_scope`.
+    /// If `ret_typ` is None, the body has unit type.
+    fn generate_lambda(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        param_typs: &[Type],
+        ret_typ: Option<Type>,
+    ) -> Result<Lambda> {
+        let (_, lambda_scope) = self.get_next_identifier(IDKinds::Block, parent_scope);
+        let mut params = Vec::new();
+        for typ in param_typs.iter() {
+            let (var, _) = self.get_next_identifier(IDKinds::Var, &lambda_scope);
+            self.env_mut().type_pool.insert_mapping(&var, typ);
+            self.env_mut().live_vars.mark_alive(&lambda_scope, &var);
+            params.push(var);
+        }
+
+        self.env_mut().enter_lambda();
+        let body = self.generate_block(u, &lambda_scope, None, ret_typ)?;
+        self.env_mut().exit_lambda();
+        Ok(Lambda { params, body })
+    }
+
+    /// Get all lambda parameters of the current function and function values
+    /// in scope that return `typ`, together with their parameter types.
+    fn get_lambda_params_returning(
+        &self,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Vec<(Identifier, Vec<Type>)> {
+        self.env()
+            .live_variables(parent_scope, None)
+            .into_iter()
+            .filter_map(|id| match self.env().type_pool.get_type(&id) {
+                Some(Type::Lambda(lt)) if lt.ret.as_ref() == typ => Some((id, lt.params)),
+                Some(Type::Function(ft)) if ft.ret.as_ref() == typ => Some((id, ft.params)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Generate a call to one of the given lambda parameters or function values.
+    fn generate_lambda_call(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        lambda_params: &[(Identifier, Vec<Type>)],
+    ) -> Result<FunctionCall> {
+        let (name, params) = u.choose(lambda_params)?;
+        let mut args = Vec::new();
+        for typ in params.iter() {
+            args.push(self.generate_expression_of_type(u, parent_scope, typ, true, false)?);
+        }
+        // The called function may change the global storage
+        self.env_mut().storage.forget_all();
+        Ok(FunctionCall {
+            name: name.clone(),
+            type_args: TypeArgs::default(),
+            args,
+            receiver: false,
+        })
+    }
+
+    /// Generate a function type with up to two parameters of basic types.
+    /// Since the generated functions take `&signer` first, it is often the
+    /// first parameter so that they can be used as the value.
+    /// Function values are only required to have `copy` and `drop`,
+    /// closures cannot have `store`.
+    fn generate_function_type(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<FunctionType> {
+        let lt = self.generate_lambda_type(u, parent_scope)?;
+        let mut params = lt.params;
+        if bool::arbitrary(u)? {
+            params.insert(0, Type::Ref(Box::new(Type::Signer)));
+        }
+        Ok(FunctionType {
+            params,
+            ret: lt.ret,
+            abilities: vec![Ability::Copy, Ability::Drop],
+        })
+    }
+
+    /// Check if a value of `typ` is or contains a function value.
+    fn contains_function_value(&self, typ: &Type) -> bool {
+        match typ {
+            Type::Function(_) => true,
+            Type::Vector(inner) | Type::Ref(inner) | Type::MutRef(inner) => {
+                self.contains_function_value(inner)
+            },
+            Type::Struct(st) => self.struct_contains_function_value(&st.name),
+            Type::StructConcrete(st) => {
+                self.struct_contains_function_value(&st.name)
+                    || st
+                        .type_args
+                        .type_args
+                        .iter()
+                        .any(|t| self.contains_function_value(t))
+            },
+            _ => false,
+        }
+    }
+
+    fn struct_contains_function_value(&self, name: &Identifier) -> bool {
+        match self.get_struct_definition_with_identifier(name) {
+            Some(st) => st
+                .fields
+                .iter()
+                .any(|(_, t)| self.contains_function_value(t)),
+            None => false,
+        }
+    }
+
+    /// Check if the function `name` is public.
+    fn is_public_function(&self, name: &Identifier) -> bool {
+        self.modules.iter().any(|m| {
+            m.borrow().functions.iter().any(|f| {
+                let f = f.borrow();
+                &f.signature.name == name && f.visibility == Visibility::Public
+            })
+        })
+    }
+
+    /// Get the functions that can be wrapped in a closure in `parent_scope`.
+    ///
+    /// The targets are called with captured values for some of their parameters,
+    /// so they cannot be generic, take lambdas, function values or linear values,
+    /// and must return a value of a basic type. Functions with fuel are left out
+    /// as the dynamic calls could not pass the fuel on.
+    fn get_closure_targets(&self, parent_scope: &Scope) -> Vec<FunctionSignature> {
+        if parent_scope.is_root() {
+            return vec![];
+        }
+        self.get_callable_functions(parent_scope)
+            .into_iter()
+            .filter(|sig| {
+                !sig.inline
+                    && sig.fuel.is_none()
+                    && sig.type_parameters.type_parameters.is_empty()
+                    && sig.return_type.as_ref().is_some_and(|t| t.is_num_or_bool())
+                    && sig.parameters.iter().all(|(_, t)| {
+                        !matches!(t, Type::Lambda(_) | Type::Function(_)) && !self.is_linear(t)
+                    })
+            })
+            .collect()
+    }
+
+    /// Match the parameters of `func` against the parameters of a function type.
+    ///
+    /// Returns for each parameter of `func` whether it is captured, or `None` if
+    /// the `params` cannot be matched in order by the rest of the parameters.
+    /// Only values of basic types can be captured.
+    fn match_closure_params(&self, func: &FunctionSignature, params: &[Type]) -> Option<Vec<bool>> {
+        let mut remaining = params.iter().peekable();
+        let mut captured = Vec::new();
+        for (_, typ) in func.parameters.iter() {
+            match remaining.peek() {
+                Some(&next) if next == typ => {
+                    remaining.next();
+                    captured.push(false);
+                },
+                _ if typ.is_num_or_bool() => captured.push(true),
+                _ => return None,
+            }
+        }
+        match remaining.next() {
+            Some(_) => None,
+            None => Some(captured),
+        }
+    }
+
+    /// Generate a closure calling `func`, e.g. `|x| function3(x, 42)`.
+    /// The parameters marked in `captured` are given a captured value,
+    /// the others are taken from the closure parameters in order.
+    fn generate_closure(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        func: &FunctionSignature,
+        captured: &[bool],
+    ) -> Result<Expression> {
+        let (block_name, lambda_scope) = self.get_next_identifier(IDKinds::Block, parent_scope);
+        let mut params = Vec::new();
+        let mut args = Vec::new();
+        for ((_, typ), capture) in func.parameters.iter().zip(captured.iter()) {
+            let arg = match capture {
+                true => self.generate_captured_value(u, parent_scope, typ)?,
+                false => {
+                    let (var, _) = self.get_next_identifier(IDKinds::Var, &lambda_scope);
+                    self.env_mut().type_pool.insert_mapping(&var, typ);
+                    params.push(var.clone());
+                    Expression::Variable(VariableAccess {
+                        name: var,
+                        copy: false,
+                    })
+                },
+            };
+            args.push(arg);
+        }
+        let call = FunctionCall {
+            name: self.get_callee_name(parent_scope, &func.name),
+            type_args: TypeArgs::default(),
+            args,
+            receiver: false,
+        };
+        Ok(Expression::Lambda(Box::new(Lambda {
+            params,
+            body: Block {
+                name: block_name,
+                stmts: vec![],
+                return_expr: Some(Expression::FunctionCall(call)),
+            },
+        })))
+    }
+
+    /// Generate a value of the basic type `typ` to be captured by a closure.
+    /// Only literals, constants and copies of local variables are captured,
+    /// since closures cannot capture references.
+    fn generate_captured_value(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Result<Expression> {
+        let vars = self.env().live_variables(parent_scope, Some(typ));
+        let constants = self.env().constants(parent_scope, Some(typ));
+        Ok(match u.int_in_range(0..=2)? {
+            0 if !vars.is_empty() => Expression::Variable(VariableAccess {
+                name: u.choose(&vars)?.clone(),
+                copy: true,
+            }),
+            1 if !constants.is_empty() => Expression::Variable(VariableAccess {
+                name: u.choose(&constants)?.clone(),
+                copy: false,
+            }),
+            _ => match typ {
+                Type::Bool => Expression::Boolean(bool::arbitrary(u)?),
+                _ => Expression::NumberLiteral(self.generate_number_literal(
+                    u,
+                    Some(typ),
+                    None,
+                    None,
+                )?),
+            },
+        })
+    }
+
+    /// Generate a function value of type `ft`.
+    ///
+    /// The value is a copy of a variable or field, a closure of a function
+    /// in scope, or the function itself if its parameters match exactly.
+    /// Otherwise, a lambda ignoring its parameters is used.
+    fn generate_function_value(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        ft: &FunctionType,
+    ) -> Result<Expression> {
+        let typ = Type::Function(ft.clone());
+        let vars = self.env().live_variables(parent_scope, Some(&typ));
+        let has_store = ft.abilities.contains(&Ability::Store);
+        let targets = self
+            .get_closure_targets(parent_scope)
+            .into_iter()
+            .filter(|f| f.return_type.as_ref() == Some(ft.ret.as_ref()))
+            .filter_map(|f| {
+                let captured = self.match_closure_params(&f, &ft.params)?;
+                let exact = captured.iter().all(|c| !c);
+                let bare = exact
+                    && (self.is_public_function(&f.name)
+                        || self.env().id_pool.is_id_in_scope(&f.name, parent_scope));
+                match has_store && !(bare && self.is_public_function(&f.name)) {
+                    true => None,
+                    false => Some((f, captured, bare)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let weights = vec![
+            (0, if vars.is_empty() { 0 } else { 3 }),
+            (1, match self.has_field_access_of_type(parent_scope, &typ) {
+                true => 2,
+                false => 0,
+            }),
+            (2, if targets.is_empty() { 0 } else { 5 }),
+            (3, if has_store { 0 } else { 1 }),
         ];
-        choose_item_weighted(u, &op_weights)
+        let weights = weights
+            .into_iter()
+            .filter(|(_, w)| *w > 0)
+            .collect::<Vec<_>>();
+        if weights.is_empty() {
+            return self.generate_abort(u, parent_scope, Some(112233));
+        }
+        match choose_item_weighted(u, &weights)? {
+            0 => Ok(Expression::Variable(VariableAccess {
+                name: u.choose(&vars)?.clone(),
+                copy: false,
+            })),
+            1 => Ok(Expression::FieldAccess(Box::new(
+                self.generate_field_access(u, parent_scope, &typ)?,
+            ))),
+            2 => {
+                let (f, captured, bare) = u.choose(&targets)?;
+                match *bare && (has_store || bool::arbitrary(u)?) {
+                    true => Ok(Expression::FunctionValue(
+                        self.get_callee_name(parent_scope, &f.name),
+                    )),
+                    false => self.generate_closure(u, parent_scope, f, captured),
+                }
+            },
+            3 => {
+                let (block_name, lambda_scope) =
+                    self.get_next_identifier(IDKinds::Block, parent_scope);
+                let mut params = Vec::new();
+                for typ in ft.params.iter() {
+                    let (var, _) = self.get_next_identifier(IDKinds::Var, &lambda_scope);
+                    self.env_mut().type_pool.insert_mapping(&var, typ);
+                    params.push(var);
+                }
+                let same_typ = params
+                    .iter()
+                    .zip(ft.params.iter())
+                    .filter(|(_, t)| *t == ft.ret.as_ref())
+                    .map(|(p, _)| p.clone())
+                    .collect::<Vec<_>>();
+                let ret = match same_typ.is_empty() {
+                    false => Expression::Variable(VariableAccess {
+                        name: u.choose(&same_typ)?.clone(),
+                        copy: false,
+                    }),
+                    true => self.generate_captured_value(u, &lambda_scope, &ft.ret)?,
+                };
+                Ok(Expression::Lambda(Box::new(Lambda {
+                    params,
+                    body: Block {
+                        name: block_name,
+                        stmts: vec![],
+                        return_expr: Some(ret),
+                    },
+                })))
+            },
+            _ => panic!("Invalid function value kind"),
+        }
+    }
+
+    /// Generate a local variable holding a function value, e.g.
+    /// `let f: |u8| u64 has copy + drop = |x| function3(x, 42);`
+    fn generate_function_value_declaration(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<Statement> {
+        let targets = self.get_closure_targets(parent_scope);
+        let (typ, value) = match targets.is_empty() {
+            true => {
+                let ft = self.generate_function_type(u, parent_scope)?;
+                let value = self.generate_function_value(u, parent_scope, &ft)?;
+                (ft, value)
+            },
+            false => {
+                let f = u.choose(&targets)?;
+                // Functions of the module and public functions can be used as is
+                let public = self.is_public_function(&f.name);
+                let bare = (public || self.env().id_pool.is_id_in_scope(&f.name, parent_scope))
+                    && bool::arbitrary(u)?;
+                let mut captured = Vec::new();
+                for (_, typ) in f.parameters.iter() {
+                    captured.push(!bare && typ.is_num_or_bool() && bool::arbitrary(u)?);
+                }
+                let params = f
+                    .parameters
+                    .iter()
+                    .zip(captured.iter())
+                    .filter(|(_, c)| !**c)
+                    .map(|((_, t), _)| t.clone())
+                    .collect::<Vec<_>>();
+                let mut ft = FunctionType {
+                    params,
+                    ret: Box::new(f.return_type.clone().unwrap()),
+                    abilities: vec![Ability::Copy, Ability::Drop],
+                };
+                match bare {
+                    true => {
+                        // Only public functions can be stored
+                        if public {
+                            ft.abilities.push(Ability::Store);
+                        }
+                        (
+                            ft,
+                            Expression::FunctionValue(self.get_callee_name(parent_scope, &f.name)),
+                        )
+                    },
+                    false => {
+                        let value = self.generate_closure(u, parent_scope, f, &captured)?;
+                        (ft, value)
+                    },
+                }
+            },
+        };
+
+        let typ = Type::Function(typ);
+        let (name, _) = self.get_next_identifier(IDKinds::Var, parent_scope);
+        self.env_mut().type_pool.insert_mapping(&name, &typ);
+        self.env_mut().live_vars.mark_alive(parent_scope, &name);
+        Ok(Statement::Decl(Declaration {
+            typs: vec![typ],
+            names: vec![name],
+            value: Some(value),
+            emit_type: true,
+        }))
     }
 
+    /// Generate a global storage operation.
+    ///
+    /// In the straight-line code of a function body, the storage model tells
+    /// which resources are known to be published, so the operation can be
+    /// chosen to be valid and emitted without checking `exists` first.
+    /// Otherwise the operation is guarded so that it never aborts.
     fn generate_resource_operation(
         &self,
         u: &mut Unstructured,
         parent_scope: &Scope,
     ) -> Result<Statement> {
         use ResourceOperationKind as RK;
-        let kind = RK::arbitrary(u)?;
+        let mut typs = self.get_types_with_abilities(parent_scope, &[Ability::Key], true);
+        // Linear values are only moved to storage when they are consumed.
+        // Global storage operations only accept structs declared in the module,
+        // so a `key` type parameter is only ever instantiated with such a struct.
+        typs.retain(|t| !self.is_linear(t) && !t.is_type_parameter());
+
+        let straight_line = self.env().storage.is_straight_line(parent_scope);
+        let mut facts = match straight_line {
+            true => self.env().storage.known_facts(),
+            false => vec![],
+        };
+        facts.retain(|(t, _, _)| typs.contains(t));
+
+        let percent = self.env().config.known_resource_op_percent.min(100);
+        let (typ, kind, known_addr) = if !facts.is_empty()
+            && percent > 0
+            && u.ratio(percent, 100)?
+        {
+            let (typ, addr, published) = u.choose(&facts)?.clone();
+            let kind = match published {
+                true => {
+                    // The resource cannot be removed or mutably borrowed while
+                    // a reference to it may still be in use
+                    let ref_alive = !self
+                        .env()
+                        .live_variables(parent_scope, Some(&Type::Ref(Box::new(typ.clone()))))
+                        .is_empty();
+                    let mut_ref_alive = !self
+                        .env()
+                        .live_variables(parent_scope, Some(&Type::MutRef(Box::new(typ.clone()))))
+                        .is_empty();
+                    let borrowed = ref_alive || mut_ref_alive;
+                    choose_item_weighted(u, &[
+                        (RK::BorrowGlobalMut, if borrowed { 0 } else { 3 }),
+                        (RK::BorrowGlobal, if mut_ref_alive { 0 } else { 3 }),
+                        (RK::MoveFrom, if borrowed { 0 } else { 2 }),
+                        (RK::Exists, 1),
+                    ])?
+                },
+                // Only the owner of the address can publish the resource
+                false => match Self::get_signer_of_address(&addr) {
+                    Some(_) => RK::MoveTo,
+                    None => RK::Exists,
+                },
+            };
+            (typ, kind, Some(addr))
+        } else {
+            let typ = u.choose(&typs)?.clone();
+            // Publishing the resource first makes the following operations valid
+            let kind = match straight_line {
+                true => choose_item_weighted(u, &[
+                    (RK::MoveTo, 3),
+                    (RK::MoveFrom, 1),
+                    (RK::BorrowGlobal, 1),
+                    (RK::BorrowGlobalMut, 1),
+                    (RK::Exists, 1),
+                ])?,
+                false => RK::arbitrary(u)?,
+            };
+            (typ, kind, None)
+        };
+        assert!(!typ.is_some_ref());
 
         let name = match kind {
             // Only move_to does not return a value
@@ -1333,10 +4770,6 @@ impl MoveSmith {
             },
         };
 
-        let typs = self.get_types_with_abilities(parent_scope, &[Ability::Key], true);
-        let typ = u.choose(&typs)?.clone();
-        assert!(!typ.is_some_ref());
-
         // Record the type for the newly declared variable
         let ret_typ = match kind {
             RK::MoveTo => None,
@@ -1354,35 +4787,48 @@ impl MoveSmith {
 
         let mut args = vec![];
 
-        if !matches!(kind, RK::MoveTo) {
+        // The existence check must use the same address as the operation
+        let addr = if !matches!(kind, RK::MoveTo) {
             // Get address for non-move_to operations
-            args.push(self.generate_expression_of_type(
-                u,
-                parent_scope,
-                &Type::Address,
-                true,
-                false,
-            )?);
+            let addr = match known_addr {
+                Some(addr) => addr,
+                None => self.generate_address(u, parent_scope)?,
+            };
+            args.push(addr.clone());
+            addr
         } else {
             // for the move_to operation, we first need a signer
             // and an item to move
-            args.push(self.generate_expression_of_type(
-                u,
-                parent_scope,
-                &Type::Ref(Box::new(Type::Signer)),
-                true,
-                false,
-            )?);
+            let signer = match known_addr.as_ref().and_then(Self::get_signer_of_address) {
+                Some(signer) => signer,
+                None => self.generate_signer_ref(u, parent_scope)?,
+            };
+            args.push(signer.clone());
             args.push(self.generate_expression_of_type(u, parent_scope, &typ, true, true)?);
-        }
+            self.generate_address_of(signer)
+        };
+
+        // Generating the arguments may have called functions changing the storage,
+        // so the model is checked again
+        let published = match straight_line {
+            true => self.env().storage.is_published(&typ, &addr.inline()),
+            false => None,
+        };
+        let guarded = !matches!(
+            (&kind, published),
+            (RK::Exists, _)
+                | (RK::MoveTo, Some(false))
+                | (
+                    RK::MoveFrom | RK::BorrowGlobal | RK::BorrowGlobalMut,
+                    Some(true)
+                )
+        );
 
         let mut check_exists = Expression::Resource(ResourceOperation {
             kind: RK::Exists,
             typ: typ.clone(),
-            args: vec![Expression::Variable(VariableAccess {
-                name: self.env().type_pool.get_address_var(),
-                copy: false,
-            })],
+            args: vec![addr.clone()],
+            index_notation: false,
         });
 
         if matches!(kind, RK::MoveTo) {
@@ -1395,9 +4841,12 @@ impl MoveSmith {
             kind: kind.clone(),
             typ: typ.clone(),
             args: args.clone(),
+            // Index notation is only supported by Move 2
+            index_notation: self.allow_move2() && bool::arbitrary(u)?,
         });
 
         let res_op = match kind {
+            _ if !guarded => resource_expr,
             RK::MoveTo => Expression::IfElse(Box::new(IfExpr {
                 condition: check_exists,
                 body: Block {
@@ -1438,6 +4887,14 @@ impl MoveSmith {
             RK::Exists => resource_expr,
         };
 
+        // Both the guarded and the direct operations leave the resource
+        // published after move_to and removed after move_from
+        match kind {
+            RK::MoveTo => self.update_storage_model(parent_scope, &typ, &addr, true),
+            RK::MoveFrom => self.update_storage_model(parent_scope, &typ, &addr, false),
+            _ => (),
+        }
+
         Ok(match name {
             Some(name) => Statement::Decl(Declaration {
                 names: vec![name],
@@ -1449,6 +4906,38 @@ impl MoveSmith {
         })
     }
 
+    /// Record in the storage model that the resource `typ` at `addr` is
+    /// now published or removed.
+    ///
+    /// Only changes made by the straight-line code of the function body are
+    /// remembered. Code in nested blocks may run any number of times, so
+    /// everything known about the type is forgotten instead.
+    fn update_storage_model(
+        &self,
+        parent_scope: &Scope,
+        typ: &Type,
+        addr: &Expression,
+        published: bool,
+    ) {
+        let mut env = self.env_mut();
+        match env.storage.is_straight_line(parent_scope) {
+            true => env
+                .storage
+                .set_published(typ, addr.inline(), addr.clone(), published),
+            false => env.storage.forget_type(typ),
+        }
+    }
+
+    /// Get the signer of an address generated by `signer::address_of(signer)`.
+    fn get_signer_of_address(addr: &Expression) -> Option<Expression> {
+        match addr {
+            Expression::FunctionCall(call) if call.name.name == "signer::address_of" => {
+                call.args.first().cloned()
+            },
+            _ => None,
+        }
+    }
+
     /// Generate an assignment to an existing variable.
     fn generate_assignment(
         &self,
@@ -1463,9 +4952,35 @@ impl MoveSmith {
                 .unwrap();
         }
         let lhs = self.generate_assignment_lhs(u, parent_scope, &typ)?;
-        let rhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
 
-        Ok(Some(Assignment { lhs, rhs }))
+        // Numerical values can also be updated with a compound assignment, e.g. `x += 1`,
+        // which is only supported by Move 2
+        if typ.is_numerical() && self.allow_move2() && bool::arbitrary(u)? {
+            use NumericalBinaryOperator as OP;
+            let op = u
+                .choose(&[
+                    OP::Add,
+                    OP::Sub,
+                    OP::Mul,
+                    OP::Mod,
+                    OP::Div,
+                    OP::BitAnd,
+                    OP::BitOr,
+                    OP::BitXor,
+                    OP::Shl,
+                    OP::Shr,
+                ])?
+                .clone();
+            let rhs = self.generate_numerical_binop_rhs(u, parent_scope, &op, &typ)?;
+            return Ok(Some(Assignment {
+                lhs,
+                op: Some(op),
+                rhs,
+            }));
+        }
+
+        let rhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
+        Ok(Some(Assignment { lhs, op: None, rhs }))
     }
 
     /// Generate the LHS of an assignment.
@@ -1475,17 +4990,25 @@ impl MoveSmith {
         parent_scope: &Scope,
         typ: &Type,
     ) -> Result<Expression> {
-        let idents = self
-            .env()
-            .get_identifiers(Some(typ), Some(IDKinds::Var), Some(parent_scope));
+        let mut idents =
+            self.env()
+                .get_identifiers(Some(typ), Some(IDKinds::Var), Some(parent_scope));
+        idents.retain(|id| !self.env().is_read_only(id));
         let ident_weight = match idents.is_empty() {
             true => 0,
             false => 10,
         };
+        let field_weight = match self
+            .has_field_access_of_type(parent_scope, &Type::MutRef(Box::new(typ.clone())))
+        {
+            true => 10,
+            false => 0,
+        };
         let weights = [
             ("identifier", ident_weight),
             ("deref_mut", 10),
             ("deref", 10),
+            ("field", field_weight),
         ];
         let choice = choose_item_weighted(u, &weights)?;
 
@@ -1514,6 +5037,16 @@ impl MoveSmith {
                 let expr = self.generate_expression_of_type(u, parent_scope, &inner, true, true)?;
                 Ok(Expression::Dereference(Box::new(expr)))
             },
+            "field" => {
+                // Generate code like:
+                // `*&mut var.field`
+                // where the field has type `typ`
+                let inner = Type::MutRef(Box::new(typ.clone()));
+                let access = self.generate_field_access(u, parent_scope, &inner)?;
+                Ok(Expression::Dereference(Box::new(Expression::FieldAccess(
+                    Box::new(access),
+                ))))
+            },
             _ => panic!("Invalid assignment LHS choice"),
         }
     }
@@ -1643,7 +5176,7 @@ impl MoveSmith {
             3 => {
                 let call = self.generate_function_call(u, parent_scope)?;
                 match call {
-                    Some(c) => vec![Expression::FunctionCall(c)],
+                    Some(c) => vec![c],
                     None => panic!("No callable functions"),
                 }
             },
@@ -1652,12 +5185,8 @@ impl MoveSmith {
                 let assign = self.generate_assignment(u, parent_scope)?;
                 if let Some(a) = assign {
                     let mut ret = vec![Expression::Assign(Box::new(a.clone()))];
-                    match a.lhs {
-                        Expression::Variable(va) => {
-                            let record = self.generate_record_value_expr(&va.name);
-                            ret.push(record);
-                        },
-                        _ => {},
+                    if let Expression::Variable(va) = a.lhs {
+                        ret.extend(self.generate_record_value_expr(&va.name));
                     }
                     ret
                 } else {
@@ -1695,8 +5224,20 @@ impl MoveSmith {
         self.env_mut().type_depth.increase_depth();
 
         let concretized = match typ {
+            // The type argument may already be decided, e.g. while generating
+            // the arguments of a call
             Type::TypeParameter(tp) => {
-                self.concretize_type_parameter(u, tp, parent_scope, constraints, parent_type)
+                let decided = self.env().type_pool.get_concrete_type(&typ.get_name());
+                match decided {
+                    Some(concrete) => concrete,
+                    None => self.concretize_type_parameter(
+                        u,
+                        tp,
+                        parent_scope,
+                        constraints,
+                        parent_type,
+                    ),
+                }
             },
             Type::Struct(st) => self.concretize_struct(u, parent_scope, st, constraints),
             Type::Ref(inner) => {
@@ -1887,11 +5428,59 @@ impl MoveSmith {
                 value: BigUint::from(c),
                 typ: Type::U64,
             }),
-            None => self.generate_expression_of_type(u, parent_scope, &Type::U64, true, true)?,
+            None => match bool::arbitrary(u)? {
+                true => self.generate_error_code_access(parent_scope),
+                false => {
+                    self.generate_expression_of_type(u, parent_scope, &Type::U64, true, true)?
+                },
+            },
         });
         Ok(Expression::Abort(code))
     }
 
+    /// Generate an `assert!` with a random condition.
+    fn generate_assert(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<Expression> {
+        let condition =
+            self.generate_expression_of_type(u, parent_scope, &Type::Bool, true, true)?;
+        let code = self.generate_error_code_access(parent_scope);
+        Ok(Expression::Assert(Box::new(Assert { condition, code })))
+    }
+
+    /// Generate a new abort code for an assert site, so that the code
+    /// identifies where the abort happens.
+    ///
+    /// Each code has a unique value. In modules, the code is an `E_CODE*` constant
+    /// declared at the end of the generation, in scripts it is a literal.
+    fn generate_error_code_access(&self, parent_scope: &Scope) -> Expression {
+        self.generate_error_code(parent_scope).0
+    }
+
+    /// Same as `generate_error_code_access`, but also returns the value of the code.
+    fn generate_error_code(&self, parent_scope: &Scope) -> (Expression, u64) {
+        let pieces = parent_scope.to_pieces();
+        let module_scope = match pieces.first().map(String::as_str) {
+            Some("0xCAFE") if pieces.len() > 1 => Scope(Some(pieces[..2].join("::"))),
+            _ => ROOT_SCOPE,
+        };
+        let (name, _) = self.get_next_identifier(IDKinds::ErrorCode, &module_scope);
+        let code = self.error_codes.borrow().len() as u64 + 1;
+        let value = Expression::NumberLiteral(NumberLiteral {
+            value: BigUint::from(code),
+            typ: Type::U64,
+        });
+        self.env_mut().type_pool.insert_mapping(&name, &Type::U64);
+        self.error_codes.borrow_mut().push(Constant {
+            typ: Type::U64,
+            name: name.clone(),
+            value: value.clone(),
+        });
+        let access = match module_scope.is_root() {
+            true => value,
+            false => Expression::Variable(VariableAccess { name, copy: false }),
+        };
+        (access, code)
+    }
+
     /// Generate an expression of the given type or its subtype.
     ///
     /// `allow_var`: allow using variable access, this is disabled for script
@@ -1937,23 +5526,38 @@ impl MoveSmith {
         };
         trace!("Concretized type is: {:?}", typ);
 
+        // A function typed parameter can only be given a lambda
+        if let Type::Lambda(lt) = typ {
+            let lambda =
+                self.generate_lambda(u, parent_scope, &lt.params, Some(lt.ret.as_ref().clone()))?;
+            return Ok(Expression::Lambda(Box::new(lambda)));
+        }
+
+        if let Type::Function(ft) = typ {
+            return self.generate_function_value(u, parent_scope, ft);
+        }
+
+        // A value of a linear type can only be created here. Moving an existing
+        // value is left to `generate_linear_consumption`, which makes sure that
+        // each value is used exactly once on every path.
+        if self.is_linear(typ) {
+            return match typ {
+                Type::Struct(st) => self.generate_struct_pack(u, parent_scope, &st.name),
+                Type::StructConcrete(st) => self.generate_struct_pack_concrete(u, parent_scope, st),
+                // Values of a type parameter cannot be created
+                _ => self.generate_abort(u, parent_scope, Some(112233)),
+            };
+        }
+
         // Check for `&signer` and `address` types
-        // We hardcode these two types
+        // We handle these two types separately
         match typ {
             Type::Ref(inner) => {
                 if let Type::Signer = inner.as_ref() {
-                    return Ok(Expression::Variable(VariableAccess {
-                        name: self.env().type_pool.get_signer_ref_var(),
-                        copy: false,
-                    }));
+                    return self.generate_signer_ref(u, parent_scope);
                 }
             },
-            Type::Address => {
-                return Ok(Expression::Variable(VariableAccess {
-                    name: self.env().type_pool.get_address_var(),
-                    copy: false,
-                }))
-            },
+            Type::Address => return self.generate_address(u, parent_scope),
             _ => (),
         }
 
@@ -1978,6 +5582,7 @@ impl MoveSmith {
             Type::StructConcrete(st) => {
                 Some(self.generate_struct_pack_concrete(u, parent_scope, st)?)
             },
+            Type::Enum(id) => Some(self.generate_variant_pack(u, parent_scope, id)?),
             // Here we always try to concretize the type.
             // It's tricky to avoid infinite loop:
             // If the type is concretized, then it's guarenteed that the call to
@@ -2031,6 +5636,32 @@ impl MoveSmith {
                 default_choices.push(expr.clone());
                 choices.push(expr);
             }
+
+            // Module constants are only accessible within the module
+            let constants = self.env().constants(parent_scope, Some(typ));
+            if !constants.is_empty() {
+                let expr = Expression::Variable(VariableAccess {
+                    name: u.choose(&constants)?.clone(),
+                    copy: false,
+                });
+                default_choices.push(expr.clone());
+                choices.push(expr);
+            }
+
+            // A reference can also be reborrowed or frozen from another one
+            let sources = self.get_borrow_sources(parent_scope, typ, None);
+            if !sources.is_empty() {
+                let source = u.choose(&sources)?.clone();
+                let expr = self.generate_reborrow(u, &source, typ)?;
+                default_choices.push(expr.clone());
+                choices.push(expr);
+            }
+
+            // A field can also be read through an accessor of its struct
+            if let Some(expr) = self.generate_accessor_call(u, parent_scope, typ)? {
+                default_choices.push(expr.clone());
+                choices.push(expr);
+            }
         }
 
         // If the default choice is empty here and we are working on a
@@ -2089,8 +5720,13 @@ impl MoveSmith {
 
         // TODO: merge this into the other selections
         if u.ratio(2u16, 10000u16)? {
+            // Returning early would skip consuming the live linear values
             let can_use_return = match &self.env().curr_func_signature {
-                Some(sig) => !sig.inline,
+                Some(sig) => {
+                    !sig.inline
+                        && !self.env().in_lambda()
+                        && !self.has_live_linear_vars(parent_scope)
+                },
                 None => false,
             };
 
@@ -2128,12 +5764,56 @@ impl MoveSmith {
             false => 2,
         };
 
+        let cast_weight = match typ.is_numerical() {
+            true => 3,
+            false => 0,
+        };
+
+        let field_weight = match allow_var && self.has_field_access_of_type(parent_scope, typ) {
+            true => 3,
+            false => 0,
+        };
+
+        let has_enums = !self
+            .env()
+            .get_identifiers(None, Some(IDKinds::Enum), Some(parent_scope))
+            .is_empty();
+        let match_weight = match has_enums {
+            true => 2,
+            false => 0,
+        };
+        let variant_test_weight = match has_enums && typ.is_bool() {
+            true => 2,
+            false => 0,
+        };
+
+        let lambda_params = self.get_lambda_params_returning(parent_scope, typ);
+        let lambda_call_weight = match allow_call && !lambda_params.is_empty() {
+            true => 3,
+            false => 0,
+        };
+
+        let stdlib_callables = match allow_call {
+            true => self.get_stdlib_callables(typ),
+            false => vec![],
+        };
+        let stdlib_call_weight = match stdlib_callables.is_empty() {
+            true => 0,
+            false => 3,
+        };
+
         let weights = vec![
-            2,                // If-Else
-            func_call_weight, // FunctionCall
-            binop_weight,     // BinaryOperation
-            deref_weight,     // Dereference
-            2,                // Block
+            2,                   // If-Else
+            func_call_weight,    // FunctionCall
+            binop_weight,        // BinaryOperation
+            deref_weight,        // Dereference
+            2,                   // Block
+            cast_weight,         // Cast
+            field_weight,        // FieldAccess
+            match_weight,        // Match
+            variant_test_weight, // VariantTest
+            lambda_call_weight,  // Call to a lambda parameter
+            stdlib_call_weight,  // Call to a stdlib function
         ];
 
         let idx = choose_idx_weighted(u, &weights)?;
@@ -2152,7 +5832,8 @@ impl MoveSmith {
                 let func = u.choose(&callables)?;
                 let call =
                     self.generate_call_to_function(u, parent_scope, func, Some(typ), true)?;
-                choices.push(Expression::FunctionCall(call));
+                let call = self.generate_fueled_call(u, parent_scope, func, call, Some(typ))?;
+                choices.push(call);
             },
             2 => {
                 // Generate a binary operation with the given type
@@ -2171,6 +5852,44 @@ impl MoveSmith {
                 let block = self.generate_block(u, parent_scope, None, Some(typ.clone()))?;
                 choices.push(Expression::Block(Box::new(block)));
             },
+            5 => {
+                assert!(typ.is_numerical());
+                let cast = self.generate_cast(u, parent_scope, typ)?;
+                choices.push(cast);
+            },
+            6 => {
+                let access = self.generate_field_access(u, parent_scope, typ)?;
+                choices.push(Expression::FieldAccess(Box::new(access)));
+            },
+            7 => {
+                let m = self.generate_match(u, parent_scope, typ)?;
+                choices.push(Expression::Match(Box::new(m)));
+            },
+            8 => {
+                assert!(typ.is_bool());
+                let test = self.generate_variant_test(u, parent_scope)?;
+                choices.push(Expression::VariantTest(Box::new(test)));
+            },
+            9 => {
+                let call = self.generate_lambda_call(u, parent_scope, &lambda_params)?;
+                choices.push(Expression::FunctionCall(call));
+            },
+            10 => {
+                // Choose a module first since most functions are in `option`
+                let mut modules = stdlib_callables
+                    .iter()
+                    .map(|(func, _)| func.module)
+                    .collect::<Vec<&str>>();
+                modules.dedup();
+                let module = *u.choose(&modules)?;
+                let stdlib_callables = stdlib_callables
+                    .into_iter()
+                    .filter(|(func, _)| func.module == module)
+                    .collect::<Vec<_>>();
+                let (func, elem) = u.choose(&stdlib_callables)?;
+                let call = self.generate_stdlib_call(u, parent_scope, func, elem.clone())?;
+                choices.push(Expression::FunctionCall(call));
+            },
             _ => panic!("Invalid option for expression generation"),
         };
 
@@ -2184,6 +5903,199 @@ impl MoveSmith {
         Ok(u.choose(&use_choice)?.clone())
     }
 
+    /// Generate a cast expression of the numerical type `typ`.
+    ///
+    /// Most casts are widening (or to the same type) and can never abort.
+    /// With a chance of `narrowing_cast_percent`, the source type is wider
+    /// than `typ` and the cast aborts with `ARITHMETIC_ERROR` if the value
+    /// does not fit.
+    fn generate_cast(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Result<Expression> {
+        let num_bits = typ.num_bits().expect("Can only cast to numerical types");
+        let percent = self.env().config.narrowing_cast_percent.min(100);
+        let narrowing = num_bits < 256 && percent > 0 && u.ratio(percent, 100)?;
+
+        let candidates = [
+            Type::U8,
+            Type::U16,
+            Type::U32,
+            Type::U64,
+            Type::U128,
+            Type::U256,
+        ]
+        .into_iter()
+        .filter(|t| match narrowing {
+            true => t.num_bits().unwrap() > num_bits,
+            false => t.num_bits().unwrap() <= num_bits,
+        })
+        .collect::<Vec<Type>>();
+        let from = u.choose(&candidates)?;
+        trace!("Generating cast from {:?} to {:?}", from, typ);
+        self.generate_cast_from(u, parent_scope, from, typ)
+    }
+
+    /// Generate an expression of type `from` and cast it to `to`.
+    fn generate_cast_from(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        from: &Type,
+        to: &Type,
+    ) -> Result<Expression> {
+        let expr = self.generate_expression_of_type(u, parent_scope, from, true, true)?;
+        Ok(Expression::Cast(Box::new(Cast {
+            expr,
+            typ: to.clone(),
+        })))
+    }
+
+    /// Get the fields of an instantiated struct type.
+    /// Type parameters of the struct are replaced with the type arguments in `typ`.
+    ///
+    /// Returns None if `typ` is not a struct or still has uninstantiated type parameters.
+    fn get_struct_fields_of_type(&self, typ: &Type) -> Option<Vec<(Identifier, Type)>> {
+        let (name, type_args) = match typ {
+            Type::Struct(st) if st.type_parameters.type_parameters.is_empty() => {
+                (&st.name, TypeArgs::default())
+            },
+            Type::StructConcrete(st) => (&st.name, st.type_args.clone()),
+            _ => return None,
+        };
+        let struct_def = self.get_struct_definition_with_identifier(name)?;
+        let instantiate = |t: &Type| match t {
+            Type::TypeParameter(tp) => {
+                let idx = struct_def
+                    .type_parameters
+                    .find_idx_of_parameter(tp)
+                    .unwrap();
+                type_args.get_type_arg_at_idx(idx).unwrap()
+            },
+            _ => t.clone(),
+        };
+
+        let fields = struct_def
+            .fields
+            .iter()
+            .map(|(field, field_typ)| {
+                let field_typ = match field_typ {
+                    Type::StructConcrete(st) => {
+                        let mut st = st.clone();
+                        st.type_args.type_args =
+                            st.type_args.type_args.iter().map(instantiate).collect();
+                        Type::StructConcrete(st)
+                    },
+                    _ => instantiate(field_typ),
+                };
+                (field.clone(), field_typ)
+            })
+            .collect();
+        Some(fields)
+    }
+
+    /// Find all pairs of (variable, field) in scope where the variable is a
+    /// struct or a reference to a struct, and the field has type `field_typ`.
+    ///
+    /// If `mutable` is true, variables of immutable reference types are skipped
+    /// since the field cannot be mutably borrowed through them.
+    fn get_field_access_candidates(
+        &self,
+        parent_scope: &Scope,
+        field_typ: &Type,
+        mutable: bool,
+    ) -> Vec<(Identifier, Identifier)> {
+        let vars = self.env().live_variables(parent_scope, None);
+        let mut candidates = Vec::new();
+        for var in vars {
+            let var_typ = match self.env().type_pool.get_type(&var) {
+                Some(t) => t,
+                None => continue,
+            };
+            let struct_typ = match &var_typ {
+                Type::Ref(_) if mutable => continue,
+                Type::Ref(inner) | Type::MutRef(inner) => inner.as_ref().clone(),
+                _ => var_typ.clone(),
+            };
+            if let Some(fields) = self.get_struct_fields_of_type(&struct_typ) {
+                for (field, typ) in fields {
+                    if &typ == field_typ {
+                        candidates.push((var.clone(), field));
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Check if a field access can produce an expression of type `typ`.
+    fn has_field_access_of_type(&self, parent_scope: &Scope, typ: &Type) -> bool {
+        let candidates = match typ {
+            Type::Ref(inner) => self.get_field_access_candidates(parent_scope, inner, false),
+            Type::MutRef(inner) => self.get_field_access_candidates(parent_scope, inner, true),
+            _ => self.get_field_access_candidates(parent_scope, typ, false),
+        };
+        !candidates.is_empty()
+    }
+
+    /// Generate a field access of type `typ`.
+    ///
+    /// If `typ` is a reference, a field borrow `&s.f` or `&mut s.f` is generated,
+    /// otherwise the field is read by value as `s.f`.
+    /// Should only be called if `has_field_access_of_type` returns true.
+    fn generate_field_access(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Result<FieldAccess> {
+        let (kind, candidates) = match typ {
+            Type::Ref(inner) => (
+                FieldAccessKind::Borrow,
+                self.get_field_access_candidates(parent_scope, inner, false),
+            ),
+            Type::MutRef(inner) => (
+                FieldAccessKind::MutBorrow,
+                self.get_field_access_candidates(parent_scope, inner, true),
+            ),
+            _ => (
+                FieldAccessKind::Value,
+                self.get_field_access_candidates(parent_scope, typ, false),
+            ),
+        };
+        let (var, field) = u.choose(&candidates)?.clone();
+        let struct_typ = match self.env().type_pool.get_type(&var).unwrap() {
+            Type::Ref(inner) | Type::MutRef(inner) => *inner,
+            typ => typ,
+        };
+        let position = self.get_field_position(&struct_typ, &field);
+        Ok(FieldAccess {
+            kind,
+            base: Expression::Variable(VariableAccess {
+                name: var,
+                copy: false,
+            }),
+            field,
+            position,
+        })
+    }
+
+    /// Get the index of `field` if `typ` is a positional struct.
+    fn get_field_position(&self, typ: &Type, field: &Identifier) -> Option<usize> {
+        let name = match typ {
+            Type::Struct(st) => &st.name,
+            Type::StructConcrete(st) => &st.name,
+            _ => return None,
+        };
+        let struct_def = self.get_struct_definition_with_identifier(name)?;
+        match struct_def.positional {
+            true => struct_def.fields.iter().position(|(f, _)| f == field),
+            false => None,
+        }
+    }
+
     /// Generate a valid varibale access
     /// If `typ` is given, the chosen varibale will have the same type.
     #[allow(unused_assignments)]
@@ -2194,7 +6106,10 @@ impl MoveSmith {
         allow_copy: bool,
         typ: Option<&Type>,
     ) -> Result<Option<VariableAccess>> {
-        let idents = self.env().live_variables(parent_scope, typ);
+        // Linear values are only moved by `generate_linear_consumption`,
+        // so a value without copy is never implicitly copied here
+        let mut idents = self.env().live_variables(parent_scope, typ);
+        idents.retain(|id| !self.is_linear(&self.env().type_pool.get_type(id).unwrap()));
         // No live variable to use in the scope
         // TODO: consider generate a declaration with assignment here?
         if idents.is_empty() {
@@ -2226,9 +6141,79 @@ impl MoveSmith {
             copy = false;
         }
 
+        // Read-only variables are always explicitly copied so that a
+        // surrounding `&mut` can only borrow a temporary
+        if self.env().is_read_only(&chosen) {
+            copy = true;
+        }
+
         Ok(Some(VariableAccess { name: chosen, copy }))
     }
 
+    /// Choose a signer reference in scope, which is either `sref` or
+    /// one of the additional signer parameters.
+    fn generate_signer_ref(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<Expression> {
+        let signer_ref_typ = Type::Ref(Box::new(Type::Signer));
+        let mut signers = vec![self.env().type_pool.get_signer_ref_var()];
+        signers.extend(
+            self.env()
+                .live_variables(parent_scope, Some(&signer_ref_typ)),
+        );
+        let chosen = u.choose(&signers)?.clone();
+        Ok(Expression::Variable(VariableAccess {
+            name: chosen,
+            copy: false,
+        }))
+    }
+
+    /// Generate an address expression without side effects, so that it can be
+    /// repeated to check and then access the global storage at the same address.
+    ///
+    /// The address is either the `ADDR` constant of the module, a literal of
+    /// one of the accounts, or the address of a signer in scope.
+    fn generate_address(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<Expression> {
+        // Scripts and the root scope do not have the constant
+        let in_module = parent_scope.to_pieces().first().map(String::as_str) == Some("0xCAFE");
+        let mut choices = vec![
+            ("constant", if in_module { 2 } else { 0 }),
+            ("literal", 2),
+            ("signer", 2),
+        ];
+        choices.retain(|(_, w)| *w > 0);
+        Ok(match choose_item_weighted(u, &choices)? {
+            "constant" => {
+                let mut constants = vec![self.env().type_pool.get_address_var()];
+                constants.extend(self.env().constants(parent_scope, Some(&Type::Address)));
+                Expression::Variable(VariableAccess {
+                    name: u.choose(&constants)?.clone(),
+                    copy: false,
+                })
+            },
+            "literal" => {
+                let account = u.choose(&self.env().accounts)?.clone();
+                Expression::AddressLiteral(format!("@{}", account))
+            },
+            _ => {
+                let signer = self.generate_signer_ref(u, parent_scope)?;
+                self.generate_address_of(signer)
+            },
+        })
+    }
+
+    /// Generate `signer::address_of(signer)`.
+    fn generate_address_of(&self, signer: Expression) -> Expression {
+        Expression::FunctionCall(FunctionCall {
+            name: Identifier::new_str("signer::address_of", IDKinds::Function),
+            type_args: TypeArgs::default(),
+            args: vec![signer],
+            receiver: false,
+        })
+    }
+
     /// Generate a deference expression of type `typ`
     ///
     /// This function will try to select an existing variable to dereference if possible.
@@ -2407,13 +6392,38 @@ impl MoveSmith {
             },
             Some(_) => panic!("Invalid type"),
         };
-        let (lhs, rhs) = match op {
+        let lhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
+        let rhs = self.generate_numerical_binop_rhs(u, parent_scope, &op, &typ)?;
+        Ok(BinaryOperation {
+            op: BinaryOperator::Numerical(op),
+            lhs,
+            rhs,
+        })
+    }
+
+    /// Generate the RHS of a numerical binary operation whose LHS has type `typ`.
+    /// The RHS is restricted to reduce the chance of overflow, underflow,
+    /// division by zero and invalid shifts.
+    /// This is shared by binary operations and compound assignments.
+    fn generate_numerical_binop_rhs(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        op: &NumericalBinaryOperator,
+        typ: &Type,
+    ) -> Result<Expression> {
+        use NumericalBinaryOperator as OP;
+        Ok(match op {
             // Sum can overflow. Sub can underflow.
             // To reduce the chance these happend, only pick a RHS from a smaller type.
-            // TODO: currently RHS can only be a number literal
-            // TODO: once casting is supported, we can pick a variable with a smaller type
+            // The RHS is either a literal in the range of the next narrower type
+            // or an expression of the narrower type widened with a cast.
             OP::Add | OP::Sub => {
-                let lhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
+                if let Some(narrower) = typ.narrower_numerical() {
+                    if bool::arbitrary(u)? {
+                        return self.generate_cast_from(u, parent_scope, &narrower, typ);
+                    }
+                }
                 let value = match typ {
                     Type::U8 => BigUint::from(u.int_in_range(0..=127)? as u32),
                     Type::U16 => BigUint::from(u8::arbitrary(u)?),
@@ -2423,64 +6433,39 @@ impl MoveSmith {
                     Type::U256 => BigUint::from(u128::arbitrary(u)?),
                     _ => panic!("Invalid type"),
                 };
-                let rhs = Expression::NumberLiteral(NumberLiteral {
+                Expression::NumberLiteral(NumberLiteral {
                     value,
                     typ: typ.clone(),
-                });
-                (lhs, rhs)
+                })
             },
             // The result can overflow, we choose u8 for RHS to be extra safe
-            // TODO: can also try casting
-            OP::Mul => {
-                let lhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
-                let rhs = Expression::NumberLiteral(NumberLiteral {
+            // The RHS is either a literal or a `u8` expression widened with a cast
+            OP::Mul => match typ != &Type::U8 && bool::arbitrary(u)? {
+                true => self.generate_cast_from(u, parent_scope, &Type::U8, typ)?,
+                false => Expression::NumberLiteral(NumberLiteral {
                     value: BigUint::from(u.int_in_range(0..=255)? as u32),
                     typ: typ.clone(),
-                });
-                (lhs, rhs)
+                }),
             },
             // RHS cannot be 0
-            OP::Mod | OP::Div => {
-                let lhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
-                let rhs = Expression::NumberLiteral(self.generate_number_literal(
-                    u,
-                    Some(&typ),
-                    Some(BigUint::from(1u32)),
-                    None,
-                )?);
-                (lhs, rhs)
-            },
+            OP::Mod | OP::Div => Expression::NumberLiteral(self.generate_number_literal(
+                u,
+                Some(typ),
+                Some(BigUint::from(1u32)),
+                None,
+            )?),
             // RHS should be U8
             // Number of bits to shift should be less than the number of bits in LHS
             OP::Shl | OP::Shr => {
-                let num_bits = match typ {
-                    Type::U8 => 8,
-                    Type::U16 => 16,
-                    Type::U32 => 32,
-                    Type::U64 => 64,
-                    Type::U128 => 128,
-                    Type::U256 => 256,
-                    _ => panic!("Invalid type"),
-                };
+                let num_bits = typ.num_bits().expect("Invalid type");
                 let num_shift = u.int_in_range(0..=num_bits - 1)? as u32;
-                let lhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
-                let rhs = Expression::NumberLiteral(NumberLiteral {
+                Expression::NumberLiteral(NumberLiteral {
                     value: BigUint::from(num_shift),
                     typ: Type::U8,
-                });
-                (lhs, rhs)
+                })
             },
             // The rest is ok as long as LHS and RHS are the same type
-            _ => {
-                let lhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
-                let rhs = self.generate_expression_of_type(u, parent_scope, &typ, true, true)?;
-                (lhs, rhs)
-            },
-        };
-        Ok(BinaryOperation {
-            op: BinaryOperator::Numerical(op.clone()),
-            lhs,
-            rhs,
+            _ => self.generate_expression_of_type(u, parent_scope, typ, true, true)?,
         })
     }
 
@@ -2523,6 +6508,11 @@ impl MoveSmith {
                 .concretize_type(u, &chosen_typ, parent_scope, vec![], None)
                 .unwrap();
         }
+        // Function values are compared by the functions they were lifted to,
+        // which is up to the compiler
+        if self.contains_function_value(&chosen_typ) {
+            chosen_typ = Type::U64;
+        }
         trace!("Chosen operand type for equality check: {:?}", chosen_typ);
         let lhs = self.generate_expression_of_type(u, parent_scope, &chosen_typ, true, true)?;
         let rhs = self.generate_expression_of_type(u, parent_scope, &chosen_typ, true, true)?;
@@ -2568,6 +6558,7 @@ impl MoveSmith {
         Ok(Expression::StructPack(StructPack {
             name: struct_def.name.clone(),
             type_args,
+            positional: struct_def.positional,
             fields,
         }))
     }
@@ -2623,16 +6614,182 @@ impl MoveSmith {
                     st.type_args.type_args = new_args;
                     self.generate_struct_pack_concrete(u, parent_scope, &st)?
                 },
-                _ => self.generate_expression_of_type(u, parent_scope, typ, true, true)?,
+                _ => self.generate_expression_of_type(u, parent_scope, typ, true, true)?,
+            };
+            fields.push((name.clone(), expr));
+        }
+
+        Ok(Expression::StructPack(StructPack {
+            name: struct_def.name.clone(),
+            type_args: st_concrete.type_args.clone(),
+            positional: struct_def.positional,
+            fields,
+        }))
+    }
+
+    /// Construct a value of the enum `enum_name` with a random variant.
+    fn generate_variant_pack(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        enum_name: &Identifier,
+    ) -> Result<Expression> {
+        trace!("Generating variant pack for {:?}", enum_name.inline());
+        let enum_def = self.get_enum_definition_with_identifier(enum_name).unwrap();
+        let variant = u.choose(&enum_def.variants)?;
+
+        let mut fields = Vec::new();
+        for (name, typ) in variant.fields.iter() {
+            let expr = self.generate_expression_of_type(u, parent_scope, typ, true, true)?;
+            fields.push((name.clone(), expr));
+        }
+
+        Ok(Expression::VariantPack(VariantPack {
+            enum_name: enum_def.name.clone(),
+            variant: variant.name.clone(),
+            positional: variant.positional,
+            fields,
+        }))
+    }
+
+    /// Choose a random enum in scope.
+    /// Should only be called if there is at least one enum in scope.
+    fn choose_enum(&self, u: &mut Unstructured, parent_scope: &Scope) -> Result<EnumDefinition> {
+        let enum_ids = self
+            .env()
+            .get_identifiers(None, Some(IDKinds::Enum), Some(parent_scope));
+        let chosen = u.choose(&enum_ids)?;
+        Ok(self.get_enum_definition_with_identifier(chosen).unwrap())
+    }
+
+    /// Generate a `match` expression of type `typ` over a random enum value.
+    ///
+    /// Each variant gets its own arm unless it is left to a trailing wildcard arm.
+    /// Guarded arms do not cover their variant, so the wildcard arm is added
+    /// whenever a variant is skipped or guarded to keep the match exhaustive.
+    fn generate_match(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &Type,
+    ) -> Result<Match> {
+        let enum_def = self.choose_enum(u, parent_scope)?;
+        trace!("Generating match over enum: {:?}", enum_def.name.inline());
+        let expr =
+            self.generate_expression_of_type(u, parent_scope, &enum_def.get_type(), true, true)?;
+
+        let mut arms = Vec::new();
+        let mut needs_wildcard = false;
+        for variant in enum_def.variants.iter() {
+            if u.ratio(1, 5)? {
+                needs_wildcard = true;
+                continue;
+            }
+
+            // The guard is generated outside of the arm scope so that it does
+            // not use the variables bound by the pattern
+            let guard = match u.ratio(1, 4)? {
+                true => {
+                    needs_wildcard = true;
+                    Some(self.generate_expression_of_type(
+                        u,
+                        parent_scope,
+                        &Type::Bool,
+                        true,
+                        false,
+                    )?)
+                },
+                false => None,
             };
-            fields.push((name.clone(), expr));
+
+            let (_, arm_scope) = self.get_next_identifier(IDKinds::Block, parent_scope);
+            let pattern = self.generate_variant_pattern(u, &arm_scope, &enum_def.name, variant)?;
+            let body = self.generate_block(u, &arm_scope, None, Some(typ.clone()))?;
+            arms.push(MatchArm {
+                pattern: MatchPattern::Variant(pattern),
+                guard,
+                body,
+            });
         }
 
-        Ok(Expression::StructPack(StructPack {
-            name: struct_def.name.clone(),
-            type_args: st_concrete.type_args.clone(),
+        if needs_wildcard {
+            let body = self.generate_block(u, parent_scope, None, Some(typ.clone()))?;
+            arms.push(MatchArm {
+                pattern: MatchPattern::Wildcard,
+                guard: None,
+                body,
+            });
+        }
+        Ok(Match { expr, arms })
+    }
+
+    /// Generate a pattern that matches `variant`.
+    ///
+    /// Each field is either bound to a new variable in `arm_scope` or ignored with `_`.
+    /// The remaining fields can be omitted with a trailing `..`, which also works
+    /// for positional variants since only a suffix is omitted.
+    fn generate_variant_pattern(
+        &self,
+        u: &mut Unstructured,
+        arm_scope: &Scope,
+        enum_name: &Identifier,
+        variant: &Variant,
+    ) -> Result<VariantPattern> {
+        let mut fields = Vec::new();
+        let mut has_rest = false;
+        for (field, typ) in variant.fields.iter() {
+            let weights = [("var", 6), ("wildcard", 2), ("rest", 1)];
+            match choose_item_weighted(u, &weights)? {
+                "var" => {
+                    let (var, _) = self.get_next_identifier(IDKinds::Var, arm_scope);
+                    self.env_mut().type_pool.insert_mapping(&var, typ);
+                    self.env_mut().live_vars.mark_alive(arm_scope, &var);
+                    fields.push((field.clone(), FieldPattern::Var(var)));
+                },
+                "wildcard" => fields.push((field.clone(), FieldPattern::Wildcard)),
+                "rest" => {
+                    has_rest = true;
+                    break;
+                },
+                _ => panic!("Invalid field pattern choice"),
+            }
+        }
+
+        Ok(VariantPattern {
+            enum_name: enum_name.clone(),
+            variant: variant.name.clone(),
+            positional: variant.positional,
             fields,
-        }))
+            has_rest,
+        })
+    }
+
+    /// Generate an `is` test of a random enum value against a non-empty
+    /// subset of its variants.
+    fn generate_variant_test(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+    ) -> Result<VariantTest> {
+        let enum_def = self.choose_enum(u, parent_scope)?;
+        let expr =
+            self.generate_expression_of_type(u, parent_scope, &enum_def.get_type(), true, true)?;
+
+        let mut variants = Vec::new();
+        for variant in enum_def.variants.iter() {
+            if bool::arbitrary(u)? {
+                variants.push(variant.name.clone());
+            }
+        }
+        if variants.is_empty() {
+            variants.push(u.choose(&enum_def.variants)?.name.clone());
+        }
+
+        Ok(VariantTest {
+            expr,
+            enum_name: enum_def.name.clone(),
+            variants,
+        })
     }
 
     /// Generate a random function call.
@@ -2640,19 +6797,20 @@ impl MoveSmith {
         &self,
         u: &mut Unstructured,
         parent_scope: &Scope,
-    ) -> Result<Option<FunctionCall>> {
+    ) -> Result<Option<Expression>> {
         let callables = self.get_callable_functions(parent_scope);
         if callables.is_empty() {
             return Ok(None);
         }
 
         let func = u.choose(&callables)?.clone();
-        Ok(Some(self.generate_call_to_function(
+        let call = self.generate_call_to_function(u, parent_scope, &func, None, true)?;
+        Ok(Some(self.generate_fueled_call(
             u,
             parent_scope,
             &func,
+            call,
             None,
-            true,
         )?))
     }
 
@@ -2686,6 +6844,22 @@ impl MoveSmith {
             })
             .collect();
 
+        self.generate_call_with_type_args(u, parent_scope, func, desired_types, allow_var, None)
+    }
+
+    /// Generate a call to the given function.
+    /// `desired_types` can specify the concrete type for each type parameter.
+    /// If `moved_arg` is given as `(idx, expr)`, `expr` is used as the argument
+    /// at `idx` instead of generating a new one.
+    fn generate_call_with_type_args(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        func: &FunctionSignature,
+        desired_types: Vec<Option<Type>>,
+        allow_var: bool,
+        moved_arg: Option<(usize, Expression)>,
+    ) -> Result<FunctionCall> {
         // Concretize the type parameters of the function
         let (type_args, unregister) = self.concretize_type_parameters(
             u,
@@ -2702,21 +6876,70 @@ impl MoveSmith {
 
         // Generate arguments using the selected concrete types
         let mut args = Vec::new();
-        for (_, typ) in func.parameters.iter() {
-            let expr = self.generate_expression_of_type(u, parent_scope, typ, allow_var, false)?;
+        for (idx, (name, typ)) in func.parameters.iter().enumerate() {
+            let expr = match &moved_arg {
+                Some((moved_idx, moved)) if *moved_idx == idx => moved.clone(),
+                _ if func.fuel.as_ref() == Some(name) => {
+                    self.generate_fuel_arg(u, parent_scope, func)?
+                },
+                _ => self.generate_expression_of_type(u, parent_scope, typ, allow_var, false)?,
+            };
             args.push(expr);
         }
 
         unregister();
 
+        // A function taking `self` first can be called with the receiver syntax
+        // on a variable, which is then borrowed implicitly (Move 2 only)
+        let self_var = match args.first() {
+            Some(Expression::Reference(expr) | Expression::MutReference(expr)) => {
+                match expr.as_ref() {
+                    Expression::Variable(va) => Some(va.name.clone()),
+                    _ => None,
+                }
+            },
+            Some(Expression::Variable(va)) => Some(va.name.clone()),
+            _ => None,
+        };
+        let receiver = match self_var {
+            Some(name)
+                if self.allow_move2()
+                    && func.parameters.first().map(|(p, _)| p.name.as_str()) == Some("self")
+                    && bool::arbitrary(u)? =>
+            {
+                args[0] = Expression::Variable(VariableAccess { name, copy: false });
+                true
+            },
+            _ => false,
+        };
+
+        // The called function may change the global storage
+        self.env_mut().storage.forget_all();
         trace!("Done generating call to function: {:?}", func.name);
         Ok(FunctionCall {
-            name: func.name.clone(),
+            name: self.get_callee_name(parent_scope, &func.name),
             type_args,
             args,
+            receiver,
         })
     }
 
+    /// Get the name used to refer to the function `name` from `parent_scope`.
+    /// Functions of other modules are referred to through the module name.
+    /// Runners are generated in the root scope but only call their own module.
+    fn get_callee_name(&self, parent_scope: &Scope, name: &Identifier) -> Identifier {
+        let env = self.env();
+        let id_pool = &env.id_pool;
+        match parent_scope.is_root() || id_pool.is_id_in_scope(name, parent_scope) {
+            true => name.clone(),
+            false => {
+                let module_scope = id_pool.get_parent_scope_of(name).unwrap();
+                let module = module_scope.to_pieces().last().unwrap().clone();
+                Identifier::new(format!("{}::{}", module, name.name), IDKinds::Function)
+            },
+        }
+    }
+
     /// Concretize a list of type parameters into types.
     /// If some concrete types are desired, they should be put into `desired_types`
     /// at the corresponding index.
@@ -2748,10 +6971,20 @@ impl MoveSmith {
         for (tp, desired) in params.type_parameters.iter().zip(desired_types.into_iter()) {
             let typ_param = Type::TypeParameter(tp.clone());
             param_types.push(typ_param.clone());
+            // Unless a type is desired, linear type parameters are instantiated
+            // with types that have copy and drop. The callee still consumes such
+            // values as linear (see `generate_linear_consumption`), but the caller
+            // may drop a returned value of the type parameter, which a linear type
+            // would reject. Callers consuming the result desire linear structs,
+            // see `generate_linear_declaration`.
+            let constraints = match self.is_linear(&typ_param) {
+                true => vec![Ability::Copy, Ability::Drop],
+                false => vec![],
+            };
             let concrete_type = match desired {
                 Some(t) => t,
                 None => self
-                    .concretize_type(u, &typ_param, parent_scope, vec![], parent_type)
+                    .concretize_type(u, &typ_param, parent_scope, constraints, parent_type)
                     .unwrap_or(typ_param.clone()),
             };
             trace!("Got concretized type: {:?}", concrete_type);
@@ -2779,6 +7012,24 @@ impl MoveSmith {
         Ok((TypeArgs { type_args }, unregister))
     }
 
+    /// Replace the type parameters in `typ` with the corresponding type arguments.
+    fn instantiate_type(&self, typ: &Type, params: &TypeParameters, args: &TypeArgs) -> Type {
+        match typ {
+            Type::TypeParameter(tp) => match params.type_parameters.iter().position(|p| p == tp) {
+                Some(idx) => args.type_args[idx].clone(),
+                None => typ.clone(),
+            },
+            Type::Vector(inner) => {
+                Type::Vector(Box::new(self.instantiate_type(inner, params, args)))
+            },
+            Type::Ref(inner) => Type::Ref(Box::new(self.instantiate_type(inner, params, args))),
+            Type::MutRef(inner) => {
+                Type::MutRef(Box::new(self.instantiate_type(inner, params, args)))
+            },
+            _ => typ.clone(),
+        }
+    }
+
     /// Generate a random numerical literal.
     /// If the `typ` is `None`, a random type will be chosen.
     /// If the `typ` is `Some(Type::{U8, ..., U256})`, a literal of the given type will be used.
@@ -2858,13 +7109,20 @@ impl MoveSmith {
             let struct_ids = self
                 .env()
                 .get_identifiers(None, Some(IDKinds::Struct), Some(scope));
-            let structs = struct_ids
+            // Linear structs are only used where their values can be consumed
+            let mut structs = struct_ids
                 .iter()
                 .map(|id: &Identifier| {
                     let st = self.get_struct_definition_with_identifier(id).unwrap();
                     (st.get_type(), 1)
                 })
+                .filter(|(typ, _)| !self.is_linear(typ))
                 .collect::<Vec<(Type, u32)>>();
+            // Enums share the category and weight with structs
+            let enum_ids = self
+                .env()
+                .get_identifiers(None, Some(IDKinds::Enum), Some(scope));
+            structs.extend(enum_ids.iter().map(|id| (Type::Enum(id.clone()), 1)));
             if !structs.is_empty() {
                 categories.push(structs);
                 category_weights.push(10);
@@ -2879,6 +7137,7 @@ impl MoveSmith {
                 .get_identifiers(None, Some(IDKinds::TypeParameter), Some(scope))
                 .into_iter()
                 .map(|id| self.env().type_pool.get_type(&id).unwrap())
+                .filter(|typ| !self.is_linear(typ))
                 .collect::<Vec<Type>>();
 
             // Filter out types that are not instantiatable (type param not in args)
@@ -2956,11 +7215,20 @@ impl MoveSmith {
     /// will be returned.
     fn get_callable_functions(&self, scope: &Scope) -> Vec<FunctionSignature> {
         let caller_num: usize = self.get_function_num(&scope.clone().0.unwrap_or("".to_string()));
+        let caller_has_fuel = self.get_caller_fuel().is_some();
         let mut callable = Vec::new();
         for m in self.modules.iter() {
             for f in m.borrow().functions.iter() {
                 let sig = f.borrow().signature.clone();
                 if self.env().id_pool.is_id_in_scope(&sig.name, scope) {
+                    // A function with fuel only calls the functions with fuel in its
+                    // module, so that every cycle of calls consumes the fuel
+                    if caller_has_fuel {
+                        if sig.fuel.is_some() {
+                            callable.push(sig);
+                        }
+                        continue;
+                    }
                     // Note: heuristic hack to avoid recursive calls
                     // Only allow function with smaller name to call function with larger name
                     // While recursive calls are interesting, they waste fuzzing time
@@ -2972,12 +7240,396 @@ impl MoveSmith {
                         }
                     }
                     callable.push(sig);
+                } else if self.is_callable_across_modules(scope, &m.borrow(), &f.borrow()) {
+                    // Modules form a DAG, so calls to other modules cannot be recursive
+                    callable.push(sig);
                 }
             }
         }
         callable
     }
 
+    /// Get the fuel parameter of the function being generated, if any.
+    fn get_caller_fuel(&self) -> Option<Identifier> {
+        self.env()
+            .curr_func_signature
+            .as_ref()
+            .and_then(|sig| sig.fuel.clone())
+    }
+
+    /// Get the fuel of the caller if it must be passed on to `callee`,
+    /// i.e. both have fuel and are in the same module.
+    fn get_fuel_to_pass(&self, scope: &Scope, callee: &FunctionSignature) -> Option<Identifier> {
+        match callee.fuel.is_some() && self.env().id_pool.is_id_in_scope(&callee.name, scope) {
+            true => self.get_caller_fuel(),
+            false => None,
+        }
+    }
+
+    /// Generate the fuel argument of a call to `callee`.
+    /// The caller either passes on its fuel minus one, or gives a small literal.
+    fn generate_fuel_arg(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        callee: &FunctionSignature,
+    ) -> Result<Expression> {
+        let one = Expression::NumberLiteral(NumberLiteral {
+            value: BigUint::from(1u32),
+            typ: Type::U64,
+        });
+        Ok(match self.get_fuel_to_pass(parent_scope, callee) {
+            Some(fuel) => Expression::BinaryOperation(Box::new(BinaryOperation {
+                op: BinaryOperator::Numerical(NumericalBinaryOperator::Sub),
+                lhs: Expression::Variable(VariableAccess {
+                    name: fuel,
+                    copy: false,
+                }),
+                rhs: one,
+            })),
+            None => {
+                let max_fuel = self.env().config.max_fuel;
+                Expression::NumberLiteral(NumberLiteral {
+                    value: BigUint::from(u.int_in_range(0..=max_fuel)?),
+                    typ: Type::U64,
+                })
+            },
+        })
+    }
+
+    /// Wrap a call to `callee` in `if (fuel > 0)` if it passes on the fuel of
+    /// the caller. If `typ` is given, the call is the value of type `typ` and
+    /// a fallback value is used once the fuel runs out.
+    fn generate_fueled_call(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        callee: &FunctionSignature,
+        call: FunctionCall,
+        typ: Option<&Type>,
+    ) -> Result<Expression> {
+        let fuel = match self.get_fuel_to_pass(parent_scope, callee) {
+            Some(fuel) => fuel,
+            None => return Ok(Expression::FunctionCall(call)),
+        };
+        let condition = Expression::BinaryOperation(Box::new(BinaryOperation {
+            op: BinaryOperator::Numerical(NumericalBinaryOperator::Ge),
+            lhs: Expression::Variable(VariableAccess {
+                name: fuel,
+                copy: false,
+            }),
+            rhs: Expression::NumberLiteral(NumberLiteral {
+                value: BigUint::from(0u32),
+                typ: Type::U64,
+            }),
+        }));
+        let block_name = Identifier::new_str("_fuel_check", IDKinds::Block);
+        let if_expr = match typ {
+            None => IfExpr {
+                condition,
+                body: Block {
+                    name: block_name,
+                    stmts: vec![Statement::Expr(Expression::FunctionCall(call))],
+                    return_expr: None,
+                },
+                else_expr: None,
+            },
+            Some(typ) => {
+                let fallback =
+                    self.generate_expression_of_type(u, parent_scope, typ, true, false)?;
+                IfExpr {
+                    condition,
+                    body: Block {
+                        name: block_name.clone(),
+                        stmts: vec![],
+                        return_expr: Some(Expression::FunctionCall(call)),
+                    },
+                    else_expr: Some(ElseExpr {
+                        typ: Some(typ.clone()),
+                        body: Block {
+                            name: block_name,
+                            stmts: vec![],
+                            return_expr: Some(fallback),
+                        },
+                    }),
+                }
+            },
+        };
+        Ok(Expression::IfElse(Box::new(if_expr)))
+    }
+
+    /// Get the stdlib functions that can return a value of type `typ`, together
+    /// with the instantiation of the type parameter if `typ` determines it.
+    fn get_stdlib_callables(&self, typ: &Type) -> Vec<(&'static StdFunction, Option<Type>)> {
+        let target = StdType::from_type(typ);
+        STDLIB
+            .iter()
+            .filter_map(|func| {
+                let elem = func.unify_return(&target)?;
+                match (&elem, &func.type_param) {
+                    (Some(e), Some(constraints)) if !self.is_valid_stdlib_elem(e, constraints) => {
+                        None
+                    },
+                    _ => Some((func, elem)),
+                }
+            })
+            .collect()
+    }
+
+    /// Check if `typ` can instantiate the type parameter of a stdlib function.
+    /// The stdlib structs holding the value are dropped as temporaries,
+    /// so `copy` and `drop` are required in addition to the constraints.
+    fn is_valid_stdlib_elem(&self, typ: &Type, constraints: &[Ability]) -> bool {
+        match typ {
+            Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::U128
+            | Type::U256
+            | Type::Bool
+            | Type::Address => !constraints.contains(&Ability::Key),
+            Type::Vector(inner) => {
+                !constraints.contains(&Ability::Key)
+                    && self.is_valid_stdlib_elem(inner, constraints)
+            },
+            Type::Struct(StructType {
+                type_parameters, ..
+            }) if !type_parameters.type_parameters.is_empty() => false,
+            Type::Struct(_) | Type::Enum(_) | Type::TypeParameter(_) => {
+                let abilities = self.derive_abilities_of_type(typ);
+                // Closures cannot be serialized by `bcs::to_bytes`
+                !self.contains_function_value(typ)
+                    && [Ability::Copy, Ability::Drop]
+                        .iter()
+                        .chain(constraints)
+                        .all(|a| abilities.contains(a))
+            },
+            _ => false,
+        }
+    }
+
+    /// Generate a call to a stdlib function.
+    /// The type parameter is randomly instantiated if `elem` is not given.
+    fn generate_stdlib_call(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        func: &StdFunction,
+        elem: Option<Type>,
+    ) -> Result<FunctionCall> {
+        let elem = match (&func.type_param, elem) {
+            (None, _) => None,
+            (Some(_), Some(elem)) => Some(elem),
+            (Some(constraints), None) => {
+                let typ =
+                    self.get_random_type(u, parent_scope, true, false, false, false, false)?;
+                match self.is_valid_stdlib_elem(&typ, constraints) {
+                    true => Some(typ),
+                    false => Some(Type::U64),
+                }
+            },
+        };
+
+        let mut args = Vec::new();
+        for param in func.params.iter() {
+            let param = match &elem {
+                Some(elem) => param.instantiate(elem),
+                None => param.clone(),
+            };
+            args.push(self.generate_stdlib_arg(u, parent_scope, &param)?);
+        }
+        Ok(FunctionCall {
+            name: Identifier::new(func.full_name(), IDKinds::Function),
+            type_args: TypeArgs {
+                type_args: elem.into_iter().collect(),
+            },
+            args,
+            receiver: false,
+        })
+    }
+
+    /// Generate an argument of a stdlib call.
+    /// Values of stdlib structs can only be created by nested stdlib calls.
+    fn generate_stdlib_arg(
+        &self,
+        u: &mut Unstructured,
+        parent_scope: &Scope,
+        typ: &StdType,
+    ) -> Result<Expression> {
+        match typ {
+            StdType::Bounded(min, max) => {
+                Ok(Expression::NumberLiteral(self.generate_number_literal(
+                    u,
+                    Some(&Type::U64),
+                    Some(BigUint::from(*min)),
+                    Some(BigUint::from(*max)),
+                )?))
+            },
+            StdType::Utf8 => {
+                // Printable ASCII without the characters that need escaping
+                let mut s = String::new();
+                let num_bytes = self.env().config.hex_byte_str_size.select(u)?;
+                for _ in 0..num_bytes {
+                    let c = u.int_in_range(32..=126)? as u8 as char;
+                    if c != '"' && c != '\\' {
+                        s.push(c);
+                    }
+                }
+                Ok(Expression::VectorLiteral(VectorLiteral::ByteString(s)))
+            },
+            StdType::Ref(inner) if inner.is_opaque() => Ok(Expression::Reference(Box::new(
+                self.generate_stdlib_arg(u, parent_scope, inner)?,
+            ))),
+            StdType::MutRef(inner) if inner.is_opaque() => Ok(Expression::MutReference(Box::new(
+                self.generate_stdlib_arg(u, parent_scope, inner)?,
+            ))),
+            _ => match typ.to_type() {
+                Some(typ) => self.generate_expression_of_type(u, parent_scope, &typ, true, true),
+                None => {
+                    let mut producers = STDLIB
+                        .iter()
+                        .filter_map(|func| Some((func, func.unify_return(typ)?)))
+                        .collect::<Vec<_>>();
+                    // Stop nesting the calls after reaching the depth limit
+                    if self.env().expr_depth.reached_depth_limit() {
+                        producers.retain(|(func, _)| !func.params.iter().any(|p| p.is_opaque()));
+                    }
+                    let (func, elem) = u.choose(&producers)?;
+                    self.env_mut().expr_depth.increase_depth();
+                    let call = self.generate_stdlib_call(u, parent_scope, func, elem.clone());
+                    self.env_mut().expr_depth.decrease_depth();
+                    Ok(Expression::FunctionCall(call?))
+                },
+            },
+        }
+    }
+
+    /// Check if a function of another module can be called from `scope`.
+    ///
+    /// The module of `scope` must use the callee module and the callee must
+    /// not be private.
+    fn is_callable_across_modules(
+        &self,
+        scope: &Scope,
+        callee_module: &Module,
+        callee: &Function,
+    ) -> bool {
+        let caller_module = match scope.to_pieces().get(1) {
+            Some(name) => name.clone(),
+            None => return false,
+        };
+        let uses_callee_module = self.modules.iter().any(|m| {
+            let m = m.borrow();
+            m.name.name == caller_module && m.uses.iter().any(|u| u.module == callee_module.name)
+        });
+        uses_callee_module
+            && callee.visibility != Visibility::Private
+            && self.is_portable_function(callee)
+    }
+
+    /// Check if a function can be called from a script.
+    ///
+    /// Scripts can only call public functions, and only provide primitive
+    /// values, so type parameters cannot require `key` and neither lambdas nor
+    /// function values can be passed.
+    fn is_callable_from_script(&self, callee: &Function) -> bool {
+        let sig = &callee.signature;
+        callee.visibility == Visibility::Public
+            && self.is_portable_function(callee)
+            && sig
+                .type_parameters
+                .type_parameters
+                .iter()
+                .all(|tp| !tp.abilities.contains(&Ability::Key))
+            && sig
+                .parameters
+                .iter()
+                .all(|(_, typ)| !matches!(typ, Type::Lambda(_) | Type::Function(_)))
+    }
+
+    /// Check if a function can be used outside of its module.
+    ///
+    /// The signature cannot mention the structs or enums of the module,
+    /// since the caller can neither construct nor destroy their values.
+    /// Inline functions are expanded at the call site, so their bodies
+    /// cannot access anything that is only available in the module.
+    fn is_portable_function(&self, callee: &Function) -> bool {
+        let sig = &callee.signature;
+        let signature_types = sig
+            .parameters
+            .iter()
+            .map(|(_, t)| t)
+            .chain(sig.return_type.iter());
+        for typ in signature_types {
+            if !self.is_portable_type(typ) {
+                return false;
+            }
+        }
+
+        if sig.inline {
+            if callee.body.is_none() {
+                return false;
+            }
+            if !callee
+                .all_decl_types()
+                .into_iter()
+                .all(|typ| self.is_portable_type(typ))
+            {
+                return false;
+            }
+            let portable_expr = |expr: &Expression| match expr {
+                Expression::FunctionCall(call) => {
+                    self.resolve_callee(call).is_none()
+                        && call
+                            .type_args
+                            .type_args
+                            .iter()
+                            .all(|typ| self.is_portable_type(typ))
+                },
+                Expression::Variable(var) => {
+                    !matches!(var.name.kind, IDKinds::Constant | IDKinds::ErrorCode)
+                },
+                Expression::VectorLiteral(VectorLiteral::Empty(typ))
+                | Expression::VectorLiteral(VectorLiteral::Multiple(typ, _)) => {
+                    self.is_portable_type(typ)
+                },
+                Expression::VectorOperation(vop) => self.is_portable_type(&vop.elem_typ),
+                Expression::StructPack(_)
+                | Expression::FieldAccess(_)
+                | Expression::VariantPack(_)
+                | Expression::VariantTest(_)
+                | Expression::Match(_)
+                | Expression::Resource(_)
+                | Expression::FunctionValue(_) => false,
+                _ => true,
+            };
+            if !callee.all_exprs(None).into_iter().all(portable_expr) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Check if values of the type can be used outside of the module defining it,
+    /// i.e. the type does not refer to any struct or enum.
+    fn is_portable_type(&self, typ: &Type) -> bool {
+        match typ {
+            Type::Vector(inner) | Type::Ref(inner) | Type::MutRef(inner) => {
+                self.is_portable_type(inner)
+            },
+            Type::Tuple(types) => types.iter().all(|t| self.is_portable_type(t)),
+            Type::Lambda(lt) => {
+                lt.params.iter().all(|t| self.is_portable_type(t)) && self.is_portable_type(&lt.ret)
+            },
+            Type::Function(ft) => {
+                ft.params.iter().all(|t| self.is_portable_type(t)) && self.is_portable_type(&ft.ret)
+            },
+            Type::Struct(_) | Type::StructConcrete(_) | Type::Enum(_) => false,
+            _ => true,
+        }
+    }
+
     // Hacky way to get the sequence number of a function
     fn get_function_num(&self, s: &str) -> usize {
         s.split("::")
@@ -3008,6 +7660,7 @@ impl MoveSmith {
                 false => {
                     let id = match t {
                         Type::Struct(st) => &st.name,
+                        Type::Enum(id) => id,
                         Type::TypeParameter(tp) => &tp.name,
                         _ => panic!("Invalid type"),
                     };
@@ -3042,11 +7695,61 @@ impl MoveSmith {
                     .unwrap();
                 st.abilities.clone()
             },
+            Type::StructConcrete(st_typ) => {
+                let st = self
+                    .get_struct_definition_with_identifier(&st_typ.name)
+                    .unwrap();
+                st.abilities.clone()
+            },
+            Type::Enum(id) => {
+                let en = self.get_enum_definition_with_identifier(id).unwrap();
+                en.abilities.clone()
+            },
             Type::TypeParameter(tp) => tp.abilities.clone(),
+            Type::Function(ft) => ft.abilities.clone(),
             _ => Vec::from(Ability::NONE),
         }
     }
 
+    /// Check if values of the type must be used exactly once,
+    /// i.e. it is a struct or a type parameter without copy or drop.
+    fn is_linear(&self, typ: &Type) -> bool {
+        match typ {
+            Type::Struct(_) | Type::StructConcrete(_) | Type::TypeParameter(_) => {
+                let abilities = self.derive_abilities_of_type(typ);
+                !abilities.contains(&Ability::Copy) || !abilities.contains(&Ability::Drop)
+            },
+            _ => false,
+        }
+    }
+
+    /// Get the linear structs in the given scope.
+    fn get_linear_structs(&self, scope: &Scope) -> Vec<Identifier> {
+        self.env()
+            .get_identifiers(None, Some(IDKinds::Struct), Some(scope))
+            .into_iter()
+            .filter(|id| self.is_linear(&Type::new_struct(id, None)))
+            .collect()
+    }
+
+    /// Get the linear variables owned by the block `scope` that are not consumed yet.
+    fn get_owned_linear_vars(&self, scope: &Scope) -> Vec<Identifier> {
+        self.env()
+            .get_identifiers(None, Some(IDKinds::Var), Some(scope))
+            .into_iter()
+            .filter(|id| self.env().live_vars.is_live_curr(scope, id))
+            .filter(|id| self.is_linear(&self.env().type_pool.get_type(id).unwrap()))
+            .collect()
+    }
+
+    /// Check if any linear variable in `scope` or its parents is not consumed yet.
+    fn has_live_linear_vars(&self, scope: &Scope) -> bool {
+        self.env()
+            .live_variables(scope, None)
+            .iter()
+            .any(|id| self.is_linear(&self.env().type_pool.get_type(id).unwrap()))
+    }
+
     /// Helper to
     fn derive_abilities_of_var(&self, var: &Identifier) -> Vec<Ability> {
         let typ = self.env().type_pool.get_type(var).unwrap();
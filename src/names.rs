@@ -49,10 +49,13 @@ pub enum IdentifierKind {
     Var,
     Struct,
     StructConcrete,
+    Enum,
+    Variant,
     Function,
     Module,
     Script,
     Constant,
+    ErrorCode,
     Type,
     TypeParameter,
 
@@ -65,10 +68,13 @@ impl IdentifierKind {
         match name {
             _ if name.starts_with("var") => IdentifierKind::Var,
             _ if name.starts_with("Struct") => IdentifierKind::Struct,
+            _ if name.starts_with("E_CODE") => IdentifierKind::ErrorCode,
+            _ if name.starts_with("Enum") => IdentifierKind::Enum,
+            _ if name.starts_with("Variant") => IdentifierKind::Variant,
             _ if name.starts_with("function") => IdentifierKind::Function,
             _ if name.starts_with("Module") => IdentifierKind::Module,
             _ if name.starts_with("Script") => IdentifierKind::Script,
-            _ if name.starts_with("CONST") => IdentifierKind::Constant,
+            _ if name.starts_with("Constant") => IdentifierKind::Constant,
             _ if name.starts_with("_type") => IdentifierKind::Type,
             _ if name.starts_with('T') => IdentifierKind::TypeParameter,
             _ if name.starts_with("_block") => IdentifierKind::Block,
@@ -80,10 +86,13 @@ impl IdentifierKind {
         match self {
             IdentifierKind::Var => "var",
             IdentifierKind::StructConcrete | IdentifierKind::Struct => "Struct",
+            IdentifierKind::Enum => "Enum",
+            IdentifierKind::Variant => "Variant",
             IdentifierKind::Function => "function",
             IdentifierKind::Module => "Module",
             IdentifierKind::Script => "Script",
             IdentifierKind::Constant => "Constant",
+            IdentifierKind::ErrorCode => "E_CODE",
             IdentifierKind::Type => "_type",
             IdentifierKind::TypeParameter => "T",
             IdentifierKind::Block => "_block",
@@ -350,3 +359,17 @@ fn test_identifier_type() {
     let sids = id_pool.get_identifiers_of_ident_kind(IdentifierKind::Struct);
     assert!(sids.len() == 1);
 }
+
+#[test]
+fn test_loop_variable_scope() {
+    let mut id_pool = IdentifierPool::new();
+
+    let (_, func_scope) = id_pool.next_identifier(IdentifierKind::Function, &ROOT_SCOPE);
+    let (_, loop_scope) = id_pool.next_identifier(IdentifierKind::Block, &func_scope);
+    let (loop_var, _) = id_pool.next_identifier(IdentifierKind::Var, &loop_scope);
+    let (_, body_scope) = id_pool.next_identifier(IdentifierKind::Block, &loop_scope);
+
+    // The loop variable is visible in the body but not after the loop
+    assert!(id_pool.is_id_in_scope(&loop_var, &body_scope));
+    assert!(!id_pool.is_id_in_scope(&loop_var, &func_scope));
+}
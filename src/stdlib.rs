@@ -0,0 +1,382 @@
+// Copyright (c) Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! A declarative catalogue of Move stdlib functions callable from the generated code.
+//!
+//! Each entry is written as a Move-like signature, e.g.
+//! `option::get_with_default<E: copy + drop>(&Option<E>, E): E`.
+//! Functions take at most one type parameter `E` and must return a value.
+//! Functions returning references are left out since their arguments are
+//! always temporaries.
+//!
+//! Values of stdlib structs (e.g. `Option<E>` or `String`) are created and
+//! consumed within a single expression, so they never appear in the type pool.
+
+use crate::types::{Ability, Type};
+use once_cell::sync::Lazy;
+
+/// The signatures of the stdlib functions.
+/// `u64[MIN..=MAX]` is a `u64` argument limited to the range to avoid trivial aborts.
+/// `vector<u8>[utf8]` is a byte string that is valid UTF-8.
+/// Functions that abort on most arguments (e.g. `option::extract`) are left out.
+const CATALOGUE: &[&str] = &[
+    // std::option
+    "option::none<E>(): Option<E>",
+    "option::some<E>(E): Option<E>",
+    "option::is_none<E>(&Option<E>): bool",
+    "option::is_some<E>(&Option<E>): bool",
+    "option::contains<E>(&Option<E>, &E): bool",
+    "option::get_with_default<E: copy + drop>(&Option<E>, E): E",
+    "option::swap_or_fill<E>(&mut Option<E>, E): Option<E>",
+    "option::destroy_with_default<E: drop>(Option<E>, E): E",
+    "option::to_vec<E>(Option<E>): vector<E>",
+    // std::string
+    "string::utf8(vector<u8>[utf8]): String",
+    "string::length(&String): u64",
+    "string::is_empty(&String): bool",
+    "string::index_of(&String, &String): u64",
+    // std::bcs
+    "bcs::to_bytes<E>(&E): vector<u8>",
+    // std::hash
+    "hash::sha2_256(vector<u8>): vector<u8>",
+    "hash::sha3_256(vector<u8>): vector<u8>",
+    // std::signer
+    "signer::address_of(&signer): address",
+    // std::fixed_point32
+    "fixed_point32::create_from_raw_value(u64): FixedPoint32",
+    "fixed_point32::create_from_rational(u64[0..=4294967295], u64[1..=4294967295]): FixedPoint32",
+    "fixed_point32::create_from_u64(u64[0..=4294967295]): FixedPoint32",
+    "fixed_point32::get_raw_value(FixedPoint32): u64",
+    "fixed_point32::is_zero(FixedPoint32): bool",
+    "fixed_point32::multiply_u64(u64[0..=4294967295], FixedPoint32): u64",
+    "fixed_point32::min(FixedPoint32, FixedPoint32): FixedPoint32",
+    "fixed_point32::max(FixedPoint32, FixedPoint32): FixedPoint32",
+    "fixed_point32::floor(FixedPoint32): u64",
+    "fixed_point32::ceil(FixedPoint32): u64",
+    "fixed_point32::round(FixedPoint32): u64",
+    // std::bit_vector
+    // Indices stay below the minimum length so they are always in bounds
+    "bit_vector::new(u64[512..=1023]): BitVector",
+    "bit_vector::length(&BitVector): u64",
+    "bit_vector::is_index_set(&BitVector, u64[0..=511]): bool",
+    "bit_vector::longest_set_sequence_starting_at(&BitVector, u64[0..=511]): u64",
+];
+
+/// Stdlib calls with out-of-range arguments, which always abort.
+/// The abort codes are the error constants of the stdlib modules.
+pub const ABORTING_CALLS: &[AbortingCall] = &[
+    // ELENGTH: the length must be in [1, 1023]
+    AbortingCall {
+        module: "bit_vector",
+        name: "new",
+        args: &[0],
+        abort_code: 0x20001,
+    },
+    AbortingCall {
+        module: "bit_vector",
+        name: "new",
+        args: &[1024],
+        abort_code: 0x20001,
+    },
+    // EDENOMINATOR
+    AbortingCall {
+        module: "fixed_point32",
+        name: "create_from_rational",
+        args: &[1, 0],
+        abort_code: 0x10001,
+    },
+    // ERATIO_OUT_OF_RANGE: the value does not fit in 32 bits
+    AbortingCall {
+        module: "fixed_point32",
+        name: "create_from_u64",
+        args: &[u64::MAX],
+        abort_code: 0x20005,
+    },
+];
+
+/// The parsed catalogue
+pub static STDLIB: Lazy<Vec<StdFunction>> = Lazy::new(|| {
+    CATALOGUE
+        .iter()
+        .map(|sig| StdFunction::parse(sig))
+        .collect()
+});
+
+/// A type in the signature of a stdlib function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StdType {
+    /// A type that can also be used outside of stdlib calls
+    Basic(Type),
+    /// A `u64` within the inclusive range
+    Bounded(u64, u64),
+    /// A `vector<u8>` that is valid UTF-8
+    Utf8,
+    /// The type parameter of the function
+    Elem,
+    Vector(Box<StdType>),
+    Ref(Box<StdType>),
+    MutRef(Box<StdType>),
+    // Stdlib structs
+    Option(Box<StdType>),
+    String,
+    FixedPoint32,
+    BitVector,
+}
+
+impl StdType {
+    /// Convert a type of the generated code.
+    pub fn from_type(typ: &Type) -> Self {
+        match typ {
+            Type::Vector(inner) => StdType::Vector(Box::new(StdType::from_type(inner))),
+            Type::Ref(inner) => StdType::Ref(Box::new(StdType::from_type(inner))),
+            Type::MutRef(inner) => StdType::MutRef(Box::new(StdType::from_type(inner))),
+            _ => StdType::Basic(typ.clone()),
+        }
+    }
+
+    /// Convert to a type of the generated code.
+    /// Returns `None` if the type contains a stdlib struct or the type parameter.
+    pub fn to_type(&self) -> Option<Type> {
+        match self {
+            StdType::Basic(typ) => Some(typ.clone()),
+            StdType::Bounded(..) => Some(Type::U64),
+            StdType::Utf8 => Some(Type::Vector(Box::new(Type::U8))),
+            StdType::Vector(inner) => Some(Type::Vector(Box::new(inner.to_type()?))),
+            StdType::Ref(inner) => Some(Type::Ref(Box::new(inner.to_type()?))),
+            StdType::MutRef(inner) => Some(Type::MutRef(Box::new(inner.to_type()?))),
+            _ => None,
+        }
+    }
+
+    /// Check if the type is or contains a stdlib struct.
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            StdType::Vector(inner) | StdType::Ref(inner) | StdType::MutRef(inner) => {
+                inner.is_opaque()
+            },
+            StdType::Option(_) | StdType::String | StdType::FixedPoint32 | StdType::BitVector => {
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Replace the type parameter with `elem`.
+    pub fn instantiate(&self, elem: &Type) -> Self {
+        let inst = |inner: &StdType| Box::new(inner.instantiate(elem));
+        match self {
+            StdType::Elem => StdType::Basic(elem.clone()),
+            StdType::Vector(inner) => StdType::Vector(inst(inner)),
+            StdType::Ref(inner) => StdType::Ref(inst(inner)),
+            StdType::MutRef(inner) => StdType::MutRef(inst(inner)),
+            StdType::Option(inner) => StdType::Option(inst(inner)),
+            _ => self.clone(),
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        let inner = |prefix: &str| {
+            s.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix('>'))
+                .map(|rest| Box::new(StdType::parse(rest)))
+        };
+        if let Some(rest) = s.strip_prefix("&mut ") {
+            return StdType::MutRef(Box::new(StdType::parse(rest)));
+        }
+        if let Some(rest) = s.strip_prefix('&') {
+            return StdType::Ref(Box::new(StdType::parse(rest)));
+        }
+        if let Some(elem) = inner("vector<") {
+            return StdType::Vector(elem);
+        }
+        if let Some(elem) = inner("Option<") {
+            return StdType::Option(elem);
+        }
+        if let Some(range) = s.strip_prefix("u64[").and_then(|r| r.strip_suffix(']')) {
+            let (min, max) = range.split_once("..=").unwrap();
+            return StdType::Bounded(min.parse().unwrap(), max.parse().unwrap());
+        }
+        match s {
+            "vector<u8>[utf8]" => StdType::Utf8,
+            "E" => StdType::Elem,
+            "String" => StdType::String,
+            "FixedPoint32" => StdType::FixedPoint32,
+            "BitVector" => StdType::BitVector,
+            "u8" => StdType::Basic(Type::U8),
+            "u16" => StdType::Basic(Type::U16),
+            "u32" => StdType::Basic(Type::U32),
+            "u64" => StdType::Basic(Type::U64),
+            "u128" => StdType::Basic(Type::U128),
+            "u256" => StdType::Basic(Type::U256),
+            "bool" => StdType::Basic(Type::Bool),
+            "address" => StdType::Basic(Type::Address),
+            "signer" => StdType::Basic(Type::Signer),
+            _ => panic!("Unknown stdlib type: {}", s),
+        }
+    }
+}
+
+/// A call to a stdlib function that aborts with `abort_code`.
+#[derive(Debug, Clone)]
+pub struct AbortingCall {
+    pub module: &'static str,
+    pub name: &'static str,
+    pub args: &'static [u64],
+    pub abort_code: u64,
+}
+
+impl AbortingCall {
+    /// The fully qualified name of the function
+    pub fn full_name(&self) -> String {
+        format!("0x1::{}::{}", self.module, self.name)
+    }
+
+    /// The module the call aborts in
+    pub fn location(&self) -> String {
+        format!("0x1::{}", self.module)
+    }
+}
+
+/// A stdlib function in the catalogue.
+#[derive(Debug, Clone)]
+pub struct StdFunction {
+    pub module: &'static str,
+    pub name: &'static str,
+    /// The ability constraints of `E`, `None` if the function is not generic
+    pub type_param: Option<Vec<Ability>>,
+    pub params: Vec<StdType>,
+    pub ret: StdType,
+}
+
+impl StdFunction {
+    /// The fully qualified name, which does not need a `use`.
+    pub fn full_name(&self) -> String {
+        format!("0x1::{}::{}", self.module, self.name)
+    }
+
+    /// Check if the function can return a value of type `target`.
+    /// If so, also returns the instantiation of `E` if it is determined by `target`.
+    pub fn unify_return(&self, target: &StdType) -> Option<Option<Type>> {
+        let mut elem = None;
+        match unify(&self.ret, target, &mut elem) {
+            true => Some(elem),
+            false => None,
+        }
+    }
+
+    fn parse(sig: &'static str) -> Self {
+        let (path, rest) = sig.split_at(sig.find(['<', '(']).unwrap());
+        let (module, name) = path.split_once("::").unwrap();
+        let (type_param, rest) = match rest.strip_prefix("<E") {
+            Some(rest) => {
+                let (constraints, rest) = rest.split_once('>').unwrap();
+                let abilities = constraints
+                    .trim_start_matches(": ")
+                    .split(" + ")
+                    .filter(|a| !a.is_empty())
+                    .map(|a| match a {
+                        "copy" => Ability::Copy,
+                        "drop" => Ability::Drop,
+                        "store" => Ability::Store,
+                        "key" => Ability::Key,
+                        _ => panic!("Unknown ability: {}", a),
+                    })
+                    .collect();
+                (Some(abilities), rest)
+            },
+            None => (None, rest),
+        };
+        let (params, ret) = rest
+            .strip_prefix('(')
+            .and_then(|rest| rest.split_once("): "))
+            .unwrap();
+        let params = params
+            .split(", ")
+            .filter(|p| !p.is_empty())
+            .map(StdType::parse)
+            .collect();
+        Self {
+            module,
+            name,
+            type_param,
+            params,
+            ret: StdType::parse(ret),
+        }
+    }
+}
+
+/// Match `pattern` against `target` and record the instantiation of `E` in `elem`.
+fn unify(pattern: &StdType, target: &StdType, elem: &mut Option<Type>) -> bool {
+    use StdType::*;
+    match (pattern, target) {
+        (Elem, _) => match (target.to_type(), &elem) {
+            (Some(typ), Some(bound)) => &typ == bound,
+            (Some(typ), None) => {
+                *elem = Some(typ);
+                true
+            },
+            (None, _) => false,
+        },
+        (Vector(p), Vector(t))
+        | (Ref(p), Ref(t))
+        | (MutRef(p), MutRef(t))
+        | (Option(p), Option(t)) => unify(p, t, elem),
+        _ => pattern == target,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_catalogue() {
+        assert_eq!(STDLIB.len(), CATALOGUE.len());
+        let func = STDLIB
+            .iter()
+            .find(|f| f.name == "get_with_default")
+            .unwrap();
+        assert_eq!(func.module, "option");
+        assert_eq!(func.type_param, Some(vec![Ability::Copy, Ability::Drop]));
+        assert_eq!(func.params, vec![
+            StdType::Ref(Box::new(StdType::Option(Box::new(StdType::Elem)))),
+            StdType::Elem,
+        ]);
+        assert_eq!(func.ret, StdType::Elem);
+
+        let utf8 = STDLIB.iter().find(|f| f.name == "utf8").unwrap();
+        assert_eq!(utf8.params, vec![StdType::Utf8]);
+        assert_eq!(
+            utf8.params[0].to_type(),
+            Some(Type::Vector(Box::new(Type::U8)))
+        );
+    }
+
+    #[test]
+    fn test_unify_return() {
+        let to_vec = STDLIB.iter().find(|f| f.name == "to_vec").unwrap();
+        let target = StdType::from_type(&Type::Vector(Box::new(Type::U8)));
+        assert_eq!(to_vec.unify_return(&target), Some(Some(Type::U8)));
+        assert_eq!(to_vec.unify_return(&StdType::Basic(Type::U8)), None);
+
+        let utf8 = STDLIB.iter().find(|f| f.name == "utf8").unwrap();
+        assert_eq!(utf8.unify_return(&StdType::String), Some(None));
+    }
+
+    #[test]
+    fn test_aborting_calls() {
+        for call in ABORTING_CALLS {
+            let func = STDLIB
+                .iter()
+                .find(|f| f.module == call.module && f.name == call.name)
+                .unwrap();
+            assert_eq!(func.type_param, None);
+            assert_eq!(func.params.len(), call.args.len());
+            // At least one argument is outside the range generated for normal calls
+            assert!(func.params.iter().zip(call.args).any(|(p, arg)| match p {
+                StdType::Bounded(min, max) => arg < min || arg > max,
+                _ => false,
+            }));
+        }
+    }
+}
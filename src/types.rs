@@ -34,7 +34,9 @@ pub enum Type {
     // Custom types
     Struct(StructType),
     StructConcrete(StructTypeConcrete),
-    Function(Identifier),
+    Enum(Identifier),
+    Function(FunctionType),
+    Lambda(LambdaType),
 
     // Type Parameter
     TypeParameter(TypeParameter),
@@ -53,6 +55,21 @@ pub struct StructTypeConcrete {
     pub name: Identifier,
     pub type_args: TypeArgs,
 }
+/// The type of a function parameter of an inline function, e.g. `|u8, bool| u64`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LambdaType {
+    pub params: Vec<Type>,
+    pub ret: Box<Type>,
+}
+
+/// The type of a function value, e.g. `|u8, bool| u64 has copy + drop`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FunctionType {
+    pub params: Vec<Type>,
+    pub ret: Box<Type>,
+    pub abilities: Vec<Ability>,
+}
+
 /// A list of type parameters, used at struct or function definitions
 #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TypeParameters {
@@ -128,6 +145,33 @@ impl Type {
         )
     }
 
+    /// Get the number of bits of a numerical type.
+    /// Returns `None` for non-numerical types.
+    pub fn num_bits(&self) -> Option<usize> {
+        match self {
+            Type::U8 => Some(8),
+            Type::U16 => Some(16),
+            Type::U32 => Some(32),
+            Type::U64 => Some(64),
+            Type::U128 => Some(128),
+            Type::U256 => Some(256),
+            _ => None,
+        }
+    }
+
+    /// Get the next narrower numerical type, e.g. `U16` for `U32`.
+    /// Returns `None` for `U8` and non-numerical types.
+    pub fn narrower_numerical(&self) -> Option<Type> {
+        match self {
+            Type::U16 => Some(Type::U8),
+            Type::U32 => Some(Type::U16),
+            Type::U64 => Some(Type::U32),
+            Type::U128 => Some(Type::U64),
+            Type::U256 => Some(Type::U128),
+            _ => None,
+        }
+    }
+
     /// Check if the type is boolean
     pub fn is_bool(&self) -> bool {
         matches!(self, Type::Bool)
@@ -162,6 +206,27 @@ impl Type {
         matches!(self, Type::TypeParameter(_))
     }
 
+    /// Check if `typ` is this type or appears inside it
+    pub fn contains_type(&self, typ: &Type) -> bool {
+        if self == typ {
+            return true;
+        }
+        match self {
+            Type::Vector(inner) | Type::Ref(inner) | Type::MutRef(inner) => {
+                inner.contains_type(typ)
+            },
+            Type::Tuple(types) => types.iter().any(|t| t.contains_type(typ)),
+            Type::StructConcrete(st) => st.type_args.type_args.iter().any(|t| t.contains_type(typ)),
+            Type::Function(ft) => {
+                ft.params.iter().any(|t| t.contains_type(typ)) || ft.ret.contains_type(typ)
+            },
+            Type::Lambda(lt) => {
+                lt.params.iter().any(|t| t.contains_type(typ)) || lt.ret.contains_type(typ)
+            },
+            _ => false,
+        }
+    }
+
     // Check if the type is concrete
     pub fn is_concrete(&self) -> bool {
         if self.is_num_or_bool() {
@@ -176,6 +241,10 @@ impl Type {
             return st.type_parameters.type_parameters.is_empty();
         }
 
+        if let Type::Enum(_) = self {
+            return true;
+        }
+
         false
     }
 
@@ -210,7 +279,33 @@ impl Type {
             },
             Type::Struct(st) => st.name.clone(),
             Type::StructConcrete(st) => st.name.clone(),
-            Type::Function(id) => id.clone(),
+            Type::Enum(id) => id.clone(),
+            Type::Function(ft) => {
+                let params = ft
+                    .params
+                    .iter()
+                    .map(|t| t.get_name().name)
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let abilities = ft
+                    .abilities
+                    .iter()
+                    .map(|a| format!("{:?}", a))
+                    .collect::<Vec<String>>()
+                    .join("+");
+                let name = format!("|{}|{} has {}", params, ft.ret.get_name().name, abilities);
+                Identifier::new(name, IDKind::Type)
+            },
+            Type::Lambda(lt) => {
+                let params = lt
+                    .params
+                    .iter()
+                    .map(|t| t.get_name().name)
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let name = format!("|{}|{}", params, lt.ret.get_name().name);
+                Identifier::new(name, IDKind::Type)
+            },
             Type::TypeParameter(tp) => tp.name.clone(),
         }
     }